@@ -8,13 +8,24 @@ import kotlinx.serialization.json.Json
 
 @Serializable
 data class RomHeader(
-    @SerialName("prg_rom_pages") val prgRomPages: Byte,
-    @SerialName("chr_rom_pages") val chrRomPages: Byte,
-    @SerialName("prg_ram_pages") val prgRamPages: Byte,
+    val version: String,
+    @SerialName("prg_rom_pages") val prgRomPages: Short,
+    @SerialName("chr_rom_pages") val chrRomPages: Short,
+    @SerialName("prg_rom_size") val prgRomSize: Long,
+    @SerialName("chr_rom_size") val chrRomSize: Long,
+    @SerialName("prg_ram_pages") val prgRamPages: Short,
+    @SerialName("prg_ram_size") val prgRamSize: Long,
+    @SerialName("prg_nvram_size") val prgNvramSize: Long,
+    @SerialName("chr_ram_size") val chrRamSize: Long,
+    @SerialName("chr_nvram_size") val chrNvramSize: Long,
     val mirroring: String,
     val battery: Boolean,
     val trainer: Boolean,
-    val mapper: Short
+    val mapper: Short,
+    val submapper: Short,
+    @SerialName("console_type") val consoleType: String,
+    val timing: String,
+    @SerialName("expansion_device") val expansionDevice: Short
 )
 
 data class RomFile(
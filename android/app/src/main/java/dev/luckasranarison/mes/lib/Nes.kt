@@ -14,6 +14,8 @@ object Nes {
     external fun clearAudioBuffer(nes: NesPtr)
     external fun fillFrameBuffer(nes: NesPtr, buffer: IntArray, palette: ByteArray?)
     external fun setControllerState(nes: NesPtr, id: Long, state: Byte)
+    external fun saveState(nes: NesPtr): ByteArray
+    external fun loadState(nes: NesPtr, state: ByteArray)
     external fun free(nes: NesPtr)
     external fun serializeRomHeader(rom: ByteArray): String
 }
@@ -45,6 +47,8 @@ class NesObject {
     fun stepVBlank() = Nes.stepVBlank(ptr)
     fun clearAudioBuffer() = Nes.clearAudioBuffer(ptr)
     fun setControllerState(id: Long, state: Byte) = Nes.setControllerState(ptr, id, state)
+    fun saveState(): ByteArray = Nes.saveState(ptr)
+    fun loadState(state: ByteArray) = Nes.loadState(ptr, state)
 
     fun updateFrameBuffer(): IntArray {
         Nes.fillFrameBuffer(ptr, frameBuffer, colorPalette)
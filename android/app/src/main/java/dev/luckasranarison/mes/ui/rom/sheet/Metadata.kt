@@ -8,13 +8,10 @@ import androidx.compose.ui.text.font.FontWeight
 import androidx.compose.ui.text.style.TextAlign
 import androidx.compose.ui.unit.dp
 import dev.luckasranarison.mes.data.RomFile
-import dev.luckasranarison.mes.lib.CHR_ROM_PAGE_SIZE
-import dev.luckasranarison.mes.lib.PRG_RAM_SIZE
-import dev.luckasranarison.mes.lib.PRG_ROM_PAGE_SIZE
 import dev.luckasranarison.mes.ui.theme.Typography
 
-fun formatPage(count: Byte, size: Int) =
-    if (count > 0) "$count (${count * size / 1024} KB)" else "None"
+fun formatSize(size: Long) =
+    if (size > 0) "${size / 1024} KB" else "None"
 
 @Composable
 fun Metadata(key: String, value: String) {
@@ -46,10 +43,16 @@ fun MetadataList(rom: RomFile) {
 
     Metadata("Attributes", if (attributes.isEmpty()) "None" else attributes.joinToString())
     Metadata("Size", "${rom.size / 1024} KB")
+    Metadata("Format", rom.header.version)
     Metadata("Mapper", rom.header.mapper.toString())
+    Metadata("Submapper", rom.header.submapper.toString())
     Metadata("Mirroring", rom.header.mirroring)
     Metadata("Battery", if (rom.header.battery) "Yes" else "No")
-    Metadata("PRG ROM", formatPage(rom.header.prgRomPages, PRG_ROM_PAGE_SIZE))
-    Metadata("PRG RAM", formatPage(rom.header.prgRamPages, PRG_RAM_SIZE))
-    Metadata("CHR ROM", formatPage(rom.header.chrRomPages, CHR_ROM_PAGE_SIZE))
+    Metadata("Console", rom.header.consoleType)
+    Metadata("Timing", rom.header.timing)
+    Metadata("PRG ROM", formatSize(rom.header.prgRomSize))
+    Metadata("PRG RAM", formatSize(rom.header.prgRamSize))
+    Metadata("PRG NVRAM", formatSize(rom.header.prgNvramSize))
+    Metadata("CHR ROM", formatSize(rom.header.chrRomSize))
+    Metadata("CHR RAM", formatSize(rom.header.chrRamSize))
 }
\ No newline at end of file
@@ -1,6 +1,9 @@
 // https://www.nesdev.org/wiki/APU_Envelope
 
-use crate::utils::{BitFlag, Clock};
+use crate::{
+    error::Error,
+    utils::{BitFlag, Clock, Snapshot, SnapshotReader, SnapshotWriter},
+};
 
 #[derive(Debug, Default)]
 pub struct Envelope {
@@ -49,3 +52,24 @@ impl Clock for Envelope {
         }
     }
 }
+
+impl Snapshot for Envelope {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_bool(self.loop_flag);
+        state.write_bool(self.const_flag);
+        state.write_u8(self.volume);
+        state.write_bool(self.start);
+        state.write_u8(self.decay_level);
+        state.write_u8(self.counter);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.loop_flag = state.read_bool()?;
+        self.const_flag = state.read_bool()?;
+        self.volume = state.read_u8()?;
+        self.start = state.read_bool()?;
+        self.decay_level = state.read_u8()?;
+        self.counter = state.read_u8()?;
+        Ok(())
+    }
+}
@@ -1,6 +1,9 @@
 // https://www.nesdev.org/wiki/APU_Length_Counter
 
-use crate::utils::Clock;
+use crate::{
+    error::Error,
+    utils::{Clock, Snapshot, SnapshotReader, SnapshotWriter},
+};
 
 #[derive(Debug, Default)]
 pub struct LengthCounter {
@@ -48,3 +51,18 @@ impl Clock for LengthCounter {
         }
     }
 }
+
+impl Snapshot for LengthCounter {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.counter);
+        state.write_bool(self.halted);
+        state.write_bool(self.enabled);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.counter = state.read_u8()?;
+        self.halted = state.read_bool()?;
+        self.enabled = state.read_bool()?;
+        Ok(())
+    }
+}
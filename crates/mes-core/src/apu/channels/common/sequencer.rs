@@ -1,3 +1,8 @@
+use crate::{
+    error::Error,
+    utils::{Snapshot, SnapshotReader, SnapshotWriter},
+};
+
 #[derive(Debug, Default)]
 pub struct Sequencer {
     steps: usize,
@@ -21,3 +26,14 @@ impl Sequencer {
         self.current = 0;
     }
 }
+
+impl Snapshot for Sequencer {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.current as u8);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.current = state.read_u8()? as usize % self.steps.max(1);
+        Ok(())
+    }
+}
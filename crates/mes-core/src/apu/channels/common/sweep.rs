@@ -1,6 +1,9 @@
 // https://www.nesdev.org/wiki/APU_Sweep
 
-use crate::utils::BitFlag;
+use crate::{
+    error::Error,
+    utils::{BitFlag, Snapshot, SnapshotReader, SnapshotWriter},
+};
 
 use super::timer::Timer;
 
@@ -59,3 +62,24 @@ impl Sweep {
         }
     }
 }
+
+impl Snapshot for Sweep {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_bool(self.enabled);
+        state.write_u8(self.period);
+        state.write_bool(self.negate);
+        state.write_u8(self.shift);
+        state.write_u8(self.counter);
+        state.write_bool(self.reload);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.enabled = state.read_bool()?;
+        self.period = state.read_u8()?;
+        self.negate = state.read_bool()?;
+        self.shift = state.read_u8()?;
+        self.counter = state.read_u8()?;
+        self.reload = state.read_bool()?;
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
-use crate::utils::Clock;
+use crate::{
+    error::Error,
+    utils::{Clock, Snapshot, SnapshotReader, SnapshotWriter},
+};
 
 #[derive(Debug, Default)]
 pub struct Timer {
@@ -29,3 +32,16 @@ impl Clock for Timer {
         }
     }
 }
+
+impl Snapshot for Timer {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u16(self.period);
+        state.write_u16(self.counter);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.period = state.read_u16()?;
+        self.counter = state.read_u16()?;
+        Ok(())
+    }
+}
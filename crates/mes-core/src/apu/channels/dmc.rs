@@ -3,8 +3,9 @@
 // https://www.slack.net/~ant/nes-emu/apu_ref.txt
 
 use crate::{
-    mappers::{Mapper, MapperChip},
-    utils::{BitFlag, Clock},
+    error::Error,
+    mappers::MapperChip,
+    utils::{BitFlag, Clock, Snapshot, SnapshotReader, SnapshotWriter},
 };
 
 use super::common::{Channel, Timer};
@@ -37,7 +38,7 @@ impl Reader {
     }
 
     fn fetch_byte(&mut self) -> u8 {
-        let byte = self.mapper.read(self.current_address);
+        let byte = self.mapper.read(self.current_address).unwrap_or_default();
 
         self.remaining_bytes -= 1;
         self.dma_cycles = Some(4); // TODO: variable cycle length
@@ -221,3 +222,64 @@ impl Clock for Dmc {
         }
     }
 }
+
+impl Snapshot for Reader {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u16(self.sample_address);
+        state.write_u16(self.sample_length);
+        state.write_u16(self.remaining_bytes);
+        state.write_u16(self.current_address);
+        state.write_option_u8(self.dma_cycles);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.sample_address = state.read_u16()?;
+        self.sample_length = state.read_u16()?;
+        self.remaining_bytes = state.read_u16()?;
+        self.current_address = state.read_u16()?;
+        self.dma_cycles = state.read_option_u8()?;
+        Ok(())
+    }
+}
+
+impl Snapshot for OutputUnit {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_option_u8(self.buffer);
+        state.write_u8(self.shift_register);
+        state.write_u8(self.shift_counter);
+        state.write_bool(self.silence_flag);
+        state.write_u8(self.level);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.buffer = state.read_option_u8()?;
+        self.shift_register = state.read_u8()?;
+        self.shift_counter = state.read_u8()?;
+        self.silence_flag = state.read_bool()?;
+        self.level = state.read_u8()?;
+        Ok(())
+    }
+}
+
+impl Snapshot for Dmc {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_bool(self.irq_flag);
+        state.write_bool(self.interrupt);
+        state.write_bool(self.loop_flag);
+        self.reader.save(state);
+        self.output.save(state);
+        self.timer.save(state);
+        state.write_bool(self.enabled);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.irq_flag = state.read_bool()?;
+        self.interrupt = state.read_bool()?;
+        self.loop_flag = state.read_bool()?;
+        self.reader.load(state)?;
+        self.output.load(state)?;
+        self.timer.load(state)?;
+        self.enabled = state.read_bool()?;
+        Ok(())
+    }
+}
@@ -5,6 +5,7 @@ mod pulse;
 mod triangle;
 
 pub use common::Channel;
+pub(crate) use common::{Sequencer, Timer};
 pub use dmc::Dmc;
 pub use noise::Noise;
 pub use pulse::Pulse;
@@ -2,7 +2,8 @@
 
 use crate::{
     apu::frame_counter::{ClockFrame, Frame},
-    utils::{BitFlag, Clock},
+    error::Error,
+    utils::{BitFlag, Clock, Snapshot, SnapshotReader, SnapshotWriter},
 };
 
 use super::common::{Channel, Envelope, LengthCounter, Timer};
@@ -91,3 +92,22 @@ impl ClockFrame for Noise {
         }
     }
 }
+
+impl Snapshot for Noise {
+    fn save(&self, state: &mut SnapshotWriter) {
+        self.envelope.save(state);
+        self.timer.save(state);
+        self.length_counter.save(state);
+        state.write_bool(self.mode);
+        state.write_u16(self.shift);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.envelope.load(state)?;
+        self.timer.load(state)?;
+        self.length_counter.load(state)?;
+        self.mode = state.read_bool()?;
+        self.shift = state.read_u16()?;
+        Ok(())
+    }
+}
@@ -2,7 +2,8 @@
 
 use crate::{
     apu::frame_counter::{ClockFrame, Frame},
-    utils::{BitFlag, Clock},
+    error::Error,
+    utils::{BitFlag, Clock, Snapshot, SnapshotReader, SnapshotWriter},
 };
 
 use super::common::{Channel, Envelope, LengthCounter, Sequencer, Sweep, Timer};
@@ -103,3 +104,23 @@ impl ClockFrame for Pulse {
         }
     }
 }
+
+impl Snapshot for Pulse {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.duty_mode);
+        self.length_counter.save(state);
+        self.sweep.save(state);
+        self.timer.save(state);
+        self.sequencer.save(state);
+        self.envelope.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.duty_mode = state.read_u8()?;
+        self.length_counter.load(state)?;
+        self.sweep.load(state)?;
+        self.timer.load(state)?;
+        self.sequencer.load(state)?;
+        self.envelope.load(state)
+    }
+}
@@ -2,7 +2,8 @@
 
 use crate::{
     apu::frame_counter::{ClockFrame, Frame},
-    utils::{BitFlag, Clock},
+    error::Error,
+    utils::{BitFlag, Clock, Snapshot, SnapshotReader, SnapshotWriter},
 };
 
 use super::common::{Channel, LengthCounter, Sequencer, Timer};
@@ -96,3 +97,26 @@ impl ClockFrame for Triangle {
         }
     }
 }
+
+impl Snapshot for Triangle {
+    fn save(&self, state: &mut SnapshotWriter) {
+        self.timer.save(state);
+        self.length_counter.save(state);
+        self.sequencer.save(state);
+        state.write_u8(self.linear_counter);
+        state.write_u8(self.counter_reload);
+        state.write_bool(self.control_flag);
+        state.write_bool(self.reload_flag);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.timer.load(state)?;
+        self.length_counter.load(state)?;
+        self.sequencer.load(state)?;
+        self.linear_counter = state.read_u8()?;
+        self.counter_reload = state.read_u8()?;
+        self.control_flag = state.read_bool()?;
+        self.reload_flag = state.read_bool()?;
+        Ok(())
+    }
+}
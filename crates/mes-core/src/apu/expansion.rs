@@ -0,0 +1,35 @@
+// https://www.nesdev.org/wiki/Expansion_audio
+
+use std::fmt::Debug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioChip {
+    Vrc6,
+    Vrc7,
+    Mmc5,
+    Namco163,
+    Sunsoft5B,
+    Fds,
+}
+
+impl AudioChip {
+    /// Level of a full scale chip output relative to the full scale APU output.
+    pub fn relative_volume(&self) -> f32 {
+        match self {
+            AudioChip::Vrc6 => 0.6,
+            AudioChip::Vrc7 => 0.5,
+            AudioChip::Mmc5 => 0.3,
+            AudioChip::Namco163 => 0.5,
+            AudioChip::Sunsoft5B => 0.25,
+            AudioChip::Fds => 0.6,
+        }
+    }
+}
+
+/// Sound generator on the cartridge, clocked by the mapper and polled by the APU every cycle.
+pub trait ExpansionAudio: Debug {
+    fn chip(&self) -> AudioChip;
+
+    /// Current output from 0.0 to 1.0.
+    fn get_sample(&self) -> f32;
+}
@@ -1,5 +1,10 @@
 use std::{cell::RefCell, f32::consts::PI};
 
+use crate::{
+    error::Error,
+    utils::{Snapshot, SnapshotReader, SnapshotWriter},
+};
+
 const SAMPLE_RATE: f32 = 44100.0;
 
 #[derive(Debug)]
@@ -70,3 +75,21 @@ impl FilterChain {
             .fold(sample, |acc, f| f.process(acc))
     }
 }
+
+impl Snapshot for FilterChain {
+    fn save(&self, state: &mut SnapshotWriter) {
+        for filter in self.0.borrow().iter() {
+            state.write_f32(filter.prev_x);
+            state.write_f32(filter.prev_y);
+        }
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        for filter in self.0.get_mut().iter_mut() {
+            filter.prev_x = state.read_f32()?;
+            filter.prev_y = state.read_f32()?;
+        }
+
+        Ok(())
+    }
+}
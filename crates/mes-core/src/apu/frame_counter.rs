@@ -1,6 +1,9 @@
 // https://www.nesdev.org/wiki/APU_Frame_Counter
 
-use crate::utils::{BitFlag, Clock};
+use crate::{
+    error::Error,
+    utils::{BitFlag, Clock, Snapshot, SnapshotReader, SnapshotWriter},
+};
 
 mod status_flag {
     pub const I: u8 = 6;
@@ -92,3 +95,23 @@ impl Clock for FrameCounter {
         self.sequencer += 1;
     }
 }
+
+impl Snapshot for FrameCounter {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.flags);
+        state.write_u32(self.sequencer);
+        state.write_option_u8(self.frame.as_ref().map(|frame| frame.is_half() as u8));
+        state.write_bool(self.interrupt);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.flags = state.read_u8()?;
+        self.sequencer = state.read_u32()?;
+        self.frame = state.read_option_u8()?.map(|half| match half {
+            0 => Frame::Quarter,
+            _ => Frame::Half,
+        });
+        self.interrupt = state.read_bool()?;
+        Ok(())
+    }
+}
@@ -1,17 +1,21 @@
 // https://www.nesdev.org/wiki/APU
 
-mod channels;
+pub(crate) mod channels;
+mod expansion;
 mod filters;
-mod frame_counter;
+pub(crate) mod frame_counter;
 
 use channels::{Channel, Dmc, Noise, Pulse, Triangle};
 use filters::FilterChain;
 use frame_counter::{ClockFrame, FrameCounter};
 
+pub use expansion::{AudioChip, ExpansionAudio};
+
 use crate::{
     cpu::interrupt::Interrupt,
+    error::Error,
     mappers::MapperChip,
-    utils::{BitFlag, Clock},
+    utils::{BitFlag, Clock, Snapshot, SnapshotReader, SnapshotWriter},
 };
 
 #[rustfmt::skip]
@@ -34,6 +38,8 @@ pub struct Apu {
     triangle: Triangle,
     noise: Noise,
     dmc: Dmc,
+    mapper: MapperChip,
+    expansion: f32,
     frame_counter: FrameCounter,
     buffer: [f32; BUFFER_CAPACITY],
     write_index: usize,
@@ -48,7 +54,9 @@ impl Apu {
             pulse2: Pulse::channel2(),
             triangle: Triangle::new(),
             noise: Noise::new(),
-            dmc: Dmc::new(mapper),
+            dmc: Dmc::new(mapper.clone()),
+            mapper,
+            expansion: 0.0,
             frame_counter: FrameCounter::default(),
             buffer: [0.0; BUFFER_CAPACITY],
             write_index: 0,
@@ -57,15 +65,18 @@ impl Apu {
         }
     }
 
-    pub fn read_status(&mut self) -> u8 {
-        let status = (self.pulse1.is_active() as u8) << status_flag::P1
+    pub fn peek_status(&self) -> u8 {
+        (self.pulse1.is_active() as u8) << status_flag::P1
             | (self.pulse2.is_active() as u8) << status_flag::P2
             | (self.triangle.is_active() as u8) << status_flag::T
             | (self.noise.is_active() as u8) << status_flag::N
             | (self.dmc.is_active() as u8) << status_flag::D
             | (self.frame_counter.irq() as u8) << status_flag::F
-            | (self.dmc.irq() as u8) << status_flag::I;
+            | (self.dmc.irq() as u8) << status_flag::I
+    }
 
+    pub fn read_status(&mut self) -> u8 {
+        let status = self.peek_status();
         self.frame_counter.clear_irq();
 
         status
@@ -132,7 +143,8 @@ impl Apu {
     }
 
     pub fn set_mapper(&mut self, mapper: MapperChip) {
-        self.dmc.set_mapper(mapper);
+        self.dmc.set_mapper(mapper.clone());
+        self.mapper = mapper;
     }
 
     // https://www.nesdev.org/wiki/APU_Mixer
@@ -145,7 +157,7 @@ impl Apu {
 
         let pulse_out = 95.88 / ((8128.0 / (p1 + p2)) + 100.0);
         let tnd_out = 159.79 / ((1.0 / ((t / 8227.0) + (n / 12241.0) + (d / 22638.0))) + 100.0);
-        let output = pulse_out + tnd_out; // 0.0 to 1.0
+        let output = pulse_out + tnd_out + self.expansion;
 
         self.filters.process(output)
     }
@@ -164,6 +176,11 @@ impl Clock for Apu {
 
         self.frame_counter.tick();
 
+        self.expansion = self
+            .mapper
+            .expansion_audio()
+            .map_or(0.0, |(chip, sample)| sample * chip.relative_volume());
+
         if let Some(frame) = self.frame_counter.take_frame() {
             self.pulse1.tick_frame(&frame);
             self.pulse2.tick_frame(&frame);
@@ -182,3 +199,28 @@ impl Clock for Apu {
         self.cycle += 1;
     }
 }
+
+impl Snapshot for Apu {
+    fn save(&self, state: &mut SnapshotWriter) {
+        self.pulse1.save(state);
+        self.pulse2.save(state);
+        self.triangle.save(state);
+        self.noise.save(state);
+        self.dmc.save(state);
+        self.frame_counter.save(state);
+        self.filters.save(state);
+        state.write_u64(self.cycle);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.pulse1.load(state)?;
+        self.pulse2.load(state)?;
+        self.triangle.load(state)?;
+        self.noise.load(state)?;
+        self.dmc.load(state)?;
+        self.frame_counter.load(state)?;
+        self.filters.load(state)?;
+        self.cycle = state.read_u64()?;
+        Ok(())
+    }
+}
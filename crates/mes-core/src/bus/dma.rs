@@ -1,3 +1,8 @@
+use crate::{
+    error::Error,
+    utils::{Snapshot, SnapshotReader, SnapshotWriter},
+};
+
 #[derive(Debug)]
 pub struct DmaState {
     pub high_byte: u8,
@@ -18,3 +23,18 @@ impl DmaState {
         u16::from_le_bytes([self.current_page, self.high_byte])
     }
 }
+
+impl Snapshot for DmaState {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.high_byte);
+        state.write_u8(self.current_page);
+        state.write_option_u8(self.buffer);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.high_byte = state.read_u8()?;
+        self.current_page = state.read_u8()?;
+        self.buffer = state.read_option_u8()?;
+        Ok(())
+    }
+}
@@ -5,9 +5,11 @@ use crate::{
     apu::Apu,
     controller::ControllerState,
     cpu::interrupt::Interrupt,
-    mappers::{Mapper, MapperChip},
+    debugger::Debugger,
+    error::Error,
+    mappers::MapperChip,
     ppu::Ppu,
-    utils::{Clock, Reset},
+    utils::{Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
 };
 
 use std::{cell::RefCell, fmt::Debug, rc::Rc};
@@ -31,12 +33,14 @@ const RAM_SIZE: usize = 2048;
 #[derive(Debug)]
 pub struct MainBus {
     ram: [u8; RAM_SIZE],
-    mapper: MapperChip,
+    pub(crate) mapper: MapperChip,
     dma_adr: Option<u8>,
     cycle: u64,
+    open_bus: u8,
     pub(crate) apu: Rc<RefCell<Apu>>,
     pub(crate) ppu: Ppu,
     pub(crate) controller: ControllerState,
+    pub(crate) debugger: Debugger,
 }
 
 impl MainBus {
@@ -54,12 +58,25 @@ impl MainBus {
             mapper,
             dma_adr: None,
             cycle: 0,
+            open_bus: 0,
             controller,
+            debugger: Debugger::default(),
         }
     }
 
     pub fn poll_interrupt(&mut self) -> Option<Interrupt> {
-        self.ppu.poll_nmi().then_some(Interrupt::Nmi)
+        self.ppu
+            .poll_nmi()
+            .then_some(Interrupt::Nmi)
+            .or_else(|| self.poll_irq())
+    }
+
+    // The IRQ line is shared between the APU and the cartridge
+    fn poll_irq(&self) -> Option<Interrupt> {
+        self.apu
+            .borrow()
+            .poll_irq()
+            .or_else(|| self.mapper.irq().then_some(Interrupt::Irq))
     }
 
     pub fn poll_dma(&mut self) -> Option<u8> {
@@ -92,6 +109,27 @@ impl MainBus {
         self.dma_adr = Some(offset);
     }
 
+    /// Returns the value a read of `address` would return, without side effects.
+    pub fn peek_u8(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x1FFF => self.read_ram(address),
+            0x2000..=0x3FFF => self.ppu.peek_register(address),
+            0x4015 => self.apu.borrow().peek_status(),
+            0x4016 | 0x4017 => self.peek_controller(address & 1),
+            0x4020..=0xFFFF => self.mapper.read(address).unwrap_or(self.open_bus),
+            _ => self.open_bus,
+        }
+    }
+
+    /// Writes to RAM or cartridge RAM without side effects, other addresses are ignored.
+    pub fn poke_u8(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.write_ram(address, value),
+            0x4020..=0xFFFF => self.mapper.poke(address, value),
+            _ => {}
+        }
+    }
+
     fn read_ram(&self, address: u16) -> u8 {
         self.ram[address as usize & 0x07FF]
     }
@@ -100,8 +138,13 @@ impl MainBus {
         self.ram[address as usize & 0x07FF] = value;
     }
 
+    // Only the low bits are driven by the controller port, the rest is open bus
     fn read_controller(&mut self, id: u16) -> u8 {
-        self.controller.poll_button(id as usize)
+        (self.open_bus & 0xE0) | self.controller.poll_button(id as usize)
+    }
+
+    fn peek_controller(&self, id: u16) -> u8 {
+        (self.open_bus & 0xE0) | self.controller.peek_button(id as usize)
     }
 
     fn write_controller(&mut self, value: u8) {
@@ -113,21 +156,50 @@ impl MainBus {
 
 impl Bus for MainBus {
     fn read_u8(&mut self, address: u16) -> u8 {
+        let value = self.read(address);
+        self.open_bus = value;
+        self.debugger.on_read(address, value);
+        value
+    }
+
+    fn write_u8(&mut self, address: u16, value: u8) {
+        self.debugger.on_write(address, value);
+        self.dummy_write_u8(address, value);
+    }
+}
+
+impl MainBus {
+    /// Write cycle hidden from the debugger, such as the first write of a read-modify-write.
+    pub fn dummy_write_u8(&mut self, address: u16, value: u8) {
+        self.open_bus = value;
+
+        if (0x2000..0x4020).contains(&address) {
+            self.mapper.on_cpu_write(address, value);
+        }
+
+        self.write(address, value);
+    }
+
+    fn read(&mut self, address: u16) -> u8 {
         match address {
             0x0000..=0x1FFF => self.read_ram(address),
             0x2002 => self.ppu.read_status(),
             0x2004 => self.ppu.read_oam_data(),
             0x2007 => self.ppu.read_data(),
             0x2000 | 0x2001 | 0x2003 | 0x2005 | 0x2006 => self.ppu.read_buffer(),
-            0x2008..=0x3FFF => self.read_u8(address & 0x2007),
+            0x2008..=0x3FFF => self.read(address & 0x2007),
             0x4015 => self.apu.borrow_mut().read_status(),
             0x4016 | 0x4017 => self.read_controller(address & 1),
-            0x4020..=0xFFFF => self.mapper.read(address),
-            _ => panic!("Trying to read from write-only address: 0x{address:x}"),
+            0x4020..=0xFFFF => {
+                let value = self.mapper.read(address).unwrap_or(self.open_bus);
+                self.mapper.on_cpu_read(address);
+                value
+            }
+            _ => self.open_bus, // write-only and unmapped registers
         }
     }
 
-    fn write_u8(&mut self, address: u16, value: u8) {
+    fn write(&mut self, address: u16, value: u8) {
         match address {
             0x0000..=0x1FFF => self.write_ram(address, value),
             0x2000 if self.cycle >= 29_658 => self.ppu.write_ctrl(value),
@@ -138,7 +210,7 @@ impl Bus for MainBus {
             0x2006 if self.cycle >= 29_658 => self.ppu.write_addr(value),
             0x2007 => self.ppu.write_data(value),
             0x2000 | 0x2001 | 0x2005 | 0x2006 => {} // ignored before 29658 cycles
-            0x2008..=0x3FFF => self.write_u8(address & 0x2007, value),
+            0x2008..=0x3FFF => self.write(address & 0x2007, value),
             0x4000..=0x4003 => self.apu.borrow_mut().write_pulse1(address, value),
             0x4004..=0x4007 => self.apu.borrow_mut().write_pulse2(address, value),
             0x4008..=0x400B => self.apu.borrow_mut().write_triangle(address, value),
@@ -149,7 +221,7 @@ impl Bus for MainBus {
             0x4014 => self.setup_oam_dma(value),
             0x4016 => self.write_controller(value),
             0x4020..=0xFFFF => self.mapper.write(address, value),
-            _ => panic!("Trying to write to read-only address: 0x{address:x}"),
+            _ => {}
         }
     }
 }
@@ -157,6 +229,7 @@ impl Bus for MainBus {
 impl Clock for MainBus {
     fn tick(&mut self) {
         self.cycle += 1;
+        self.mapper.tick();
 
         for _ in 0..3 {
             self.ppu.tick();
@@ -173,3 +246,119 @@ impl Reset for MainBus {
         self.controller.reset();
     }
 }
+
+impl Snapshot for MainBus {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_bytes(&self.ram);
+        state.write_option_u8(self.dma_adr);
+        state.write_u64(self.cycle);
+        state.write_u8(self.open_bus);
+        self.mapper.save(state);
+        self.apu.borrow().save(state);
+        self.ppu.save(state);
+        self.controller.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        state.read_bytes(&mut self.ram)?;
+        self.dma_adr = state.read_option_u8()?;
+        self.cycle = state.read_u64()?;
+        self.open_bus = state.read_u8()?;
+        self.mapper.load(state)?;
+        self.apu.borrow_mut().load(state)?;
+        self.ppu.load(state)?;
+        self.controller.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bus, MainBus};
+    use crate::{
+        cartridge::{Cartridge, Mirroring},
+        cpu::interrupt::Interrupt,
+        error::Error,
+        mappers::{Mapper, MapperChip},
+        utils::{Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
+    };
+
+    #[derive(Debug, Default)]
+    struct IrqMapper {
+        cartridge: Cartridge,
+        cycles: u64,
+    }
+
+    impl Mapper for IrqMapper {
+        fn read(&self, _address: u16) -> Option<u8> {
+            None
+        }
+
+        fn write(&mut self, _address: u16, _value: u8) {}
+
+        fn get_mirroring(&self) -> Mirroring {
+            self.cartridge.header.mirroring
+        }
+
+        fn get_cartridge(&self) -> &Cartridge {
+            &self.cartridge
+        }
+
+        fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+            &mut self.cartridge
+        }
+
+        fn irq(&self) -> bool {
+            self.cycles >= 2
+        }
+    }
+
+    impl Clock for IrqMapper {
+        fn tick(&mut self) {
+            self.cycles += 1;
+        }
+    }
+
+    impl Snapshot for IrqMapper {
+        fn save(&self, state: &mut SnapshotWriter) {
+            state.write_u64(self.cycles);
+        }
+
+        fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+            self.cycles = state.read_u64()?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_mapper_irq() {
+        let mapper = MapperChip::new(IrqMapper::default());
+        let mut bus = MainBus::new(mapper);
+
+        bus.tick();
+
+        assert_eq!(bus.poll_interrupt(), None);
+
+        bus.tick();
+
+        assert_eq!(bus.poll_interrupt(), Some(Interrupt::Irq));
+
+        bus.reset();
+
+        assert_eq!(bus.poll_interrupt(), None);
+    }
+
+    #[test]
+    fn test_open_bus() {
+        let mut bus = MainBus::new(MapperChip::mock());
+
+        bus.write_u8(0x0000, 0x5A);
+
+        assert_eq!(bus.read_u8(0x4000), 0x5A);
+
+        bus.write_u8(0x4018, 0xFF);
+
+        assert_eq!(bus.read_u8(0x4014), 0xFF);
+        assert_eq!(bus.read_u8(0x4016), 0xE0);
+        assert_eq!(bus.read_u8(0x5000), 0xE0);
+    }
+}
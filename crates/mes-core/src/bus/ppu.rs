@@ -1,8 +1,8 @@
 use crate::{
     bus::Bus,
-    cartridge::Mirroring,
-    mappers::{Mapper, MapperChip},
-    utils::{Clock, Reset},
+    error::Error,
+    mappers::{MapperChip, Nametable},
+    utils::{Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
 };
 
 const VRAM_SIZE: usize = 2048;
@@ -17,21 +17,28 @@ pub struct PpuBus {
 
 impl Bus for PpuBus {
     fn read_u8(&mut self, address: u16) -> u8 {
+        let address = address & 0x3FFF;
+
+        if address < 0x3F00 {
+            self.mapper.on_ppu_address(address);
+        }
+
         match address {
-            0x0000..=0x1FFF => self.mapper.read(address),
-            0x2000..=0x3EFF => self.read_vram(address),
-            0x3F00..=0x3FFF => self.read_palette(address),
-            _ => self.read_u8(address & 0x3FFF),
+            0x0000..=0x1FFF if self.mapper.get_chr_ciram(address).is_none() => {
+                self.mapper.fetch_chr(address)
+            }
+            _ => self.peek_u8(address),
         }
     }
 
     fn write_u8(&mut self, address: u16, value: u8) {
-        match address {
-            0x0000..=0x1FFF => self.mapper.write(address, value),
-            0x2000..=0x3EFF => self.write_vram(address, value),
-            0x3F00..=0x3FFF => self.write_palette(address, value),
-            _ => self.write_u8(address & 0x3FFF, value),
+        let address = address & 0x3FFF;
+
+        if address < 0x3F00 {
+            self.mapper.on_ppu_address(address);
         }
+
+        self.poke_u8(address, value)
     }
 }
 
@@ -48,6 +55,34 @@ impl PpuBus {
         self.mapper = mapper;
     }
 
+    /// Reads without notifying the mapper of the address.
+    pub fn peek_u8(&self, address: u16) -> u8 {
+        let address = address & 0x3FFF;
+
+        match address {
+            0x0000..=0x1FFF => match self.mapper.get_chr_ciram(address) {
+                Some(page) => self.vram[get_vram_address(page, address)],
+                None => self.mapper.read(address).unwrap_or_default(),
+            },
+            0x2000..=0x3EFF => self.read_vram(address),
+            _ => self.read_palette(address),
+        }
+    }
+
+    /// Writes without notifying the mapper of the address.
+    pub fn poke_u8(&mut self, address: u16, value: u8) {
+        let address = address & 0x3FFF;
+
+        match address {
+            0x0000..=0x1FFF => match self.mapper.get_chr_ciram(address) {
+                Some(page) => self.vram[get_vram_address(page, address)] = value,
+                None => self.mapper.write(address, value),
+            },
+            0x2000..=0x3EFF => self.write_vram(address, value),
+            _ => self.write_palette(address, value),
+        }
+    }
+
     fn read_palette(&self, address: u16) -> u8 {
         let address = address as usize & (PALETTE_SIZE - 1);
         let address = if address == 0x10 { 0 } else { address };
@@ -61,29 +96,28 @@ impl PpuBus {
     }
 
     fn read_vram(&self, address: u16) -> u8 {
-        let vram_address = self.get_vram_address(address);
-        self.vram[vram_address as usize]
+        let address = 0x2000 | (address & 0x0FFF);
+
+        match self.mapper.get_nametable(address) {
+            Nametable::Ciram(page) => self.vram[get_vram_address(page, address)],
+            Nametable::Mapper => self.mapper.read_nametable(address),
+        }
     }
 
     fn write_vram(&mut self, address: u16, value: u8) {
-        let vram_address = self.get_vram_address(address);
-        self.vram[vram_address as usize] = value;
-    }
-
-    fn get_vram_address(&self, address: u16) -> u16 {
-        let relative_address = address & 0x0FFF;
-        let nametable_id = relative_address / 0x400;
-        let mirroring = self.mapper.get_mirroring();
-        let mirrored_address = match mirroring {
-            Mirroring::Horizontal if matches!(nametable_id, 1 | 2) => relative_address - 0x400,
-            Mirroring::OneScreen => relative_address & 0x03FF,
-            _ => relative_address,
-        };
+        let address = 0x2000 | (address & 0x0FFF);
 
-        mirrored_address & 0x07FF
+        match self.mapper.get_nametable(address) {
+            Nametable::Ciram(page) => self.vram[get_vram_address(page, address)] = value,
+            Nametable::Mapper => self.mapper.write_nametable(address, value),
+        }
     }
 }
 
+fn get_vram_address(page: u8, address: u16) -> usize {
+    (page as usize & 1) * 0x400 + (address as usize & 0x03FF)
+}
+
 impl Clock for PpuBus {}
 
 impl Reset for PpuBus {
@@ -94,10 +128,26 @@ impl Reset for PpuBus {
     }
 }
 
+impl Snapshot for PpuBus {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_bytes(&self.vram);
+        state.write_bytes(&self.palette);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        state.read_bytes(&mut self.vram)?;
+        state.read_bytes(&mut self.palette)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::PpuBus;
-    use crate::{bus::Bus, mappers::MapperChip};
+    use crate::{
+        bus::Bus,
+        cartridge::{Cartridge, Header, Mirroring},
+        mappers::MapperChip,
+    };
 
     #[test]
     fn test_ppu_bus_read_write() {
@@ -120,4 +170,52 @@ mod tests {
         assert_eq!(bus.read_u8(0x3F20), 0x10);
         assert_eq!(bus.palette[0], 0x10);
     }
+
+    #[test]
+    fn test_one_screen_upper_mirroring() {
+        let cartridge = Cartridge {
+            header: Header {
+                prg_rom_pages: 2,
+                mapper: 7,
+                ..Cartridge::default().header
+            },
+            prg_rom: vec![0; 0x8000],
+            chr_ram: vec![0; 0x2000],
+            ..Default::default()
+        };
+
+        let mut mapper = MapperChip::try_from(cartridge).unwrap();
+        let mut bus = PpuBus::new(mapper.clone());
+
+        mapper.write(0x8000, 0x10);
+        bus.write_u8(0x2000, 0x42);
+
+        assert_eq!(bus.read_u8(0x2C00), 0x42);
+        assert_eq!(bus.vram[0x400], 0x42);
+    }
+
+    #[test]
+    fn test_four_screen_vram() {
+        let cartridge = Cartridge {
+            header: Header {
+                mirroring: Mirroring::FourScreen,
+                ..Cartridge::default().header
+            },
+            vram: vec![0; 0x800],
+            ..Default::default()
+        };
+
+        let mapper = MapperChip::try_from(cartridge).unwrap();
+        let mut bus = PpuBus::new(mapper);
+
+        for (index, address) in [0x2000, 0x2400, 0x2800, 0x2C00].into_iter().enumerate() {
+            bus.write_u8(address + 5, index as u8 + 1);
+        }
+
+        assert_eq!(bus.read_u8(0x2005), 1);
+        assert_eq!(bus.read_u8(0x2405), 2);
+        assert_eq!(bus.read_u8(0x2805), 3);
+        assert_eq!(bus.read_u8(0x3C05), 4);
+        assert_eq!(bus.vram[0x405], 2);
+    }
 }
@@ -2,7 +2,7 @@
 
 use crate::{
     error::Error,
-    utils::{BitFlag, MemoryObserver},
+    utils::{BitFlag, MemoryObserver, Snapshot, SnapshotReader, SnapshotWriter},
 };
 
 #[cfg(feature = "json")]
@@ -15,9 +15,10 @@ const PRG_ROM_PAGE_SIZE: usize = 16384;
 const PRG_RAM_SIZE: usize = 8192;
 const CHR_ROM_PAGE_SIZE: usize = 8192;
 const CHR_RAM_PAGE_SIZE: usize = 8192;
+const FOUR_SCREEN_VRAM_SIZE: usize = 2048;
 
 pub fn is_ines_file(bytes: &[u8]) -> bool {
-    bytes[0..4] == INES_ASCII
+    bytes.starts_with(&INES_ASCII)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -25,20 +26,61 @@ pub fn is_ines_file(bytes: &[u8]) -> bool {
 pub enum Mirroring {
     Vertical,
     Horizontal,
-    OneScreen,
+    OneScreenLower,
+    OneScreenUpper,
     FourScreen,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub enum Version {
+    Archaic,
+    INes,
+    Nes2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub enum ConsoleType {
+    Nes,
+    VsSystem,
+    Playchoice10,
+    Extended,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub enum Timing {
+    Ntsc,
+    Pal,
+    MultiRegion,
+    Dendy,
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "json", derive(Serialize))]
 pub struct Header {
-    pub prg_rom_pages: u8,
-    pub chr_rom_pages: u8,
+    pub version: Version,
+    pub prg_rom_pages: u16,
+    pub chr_rom_pages: u16,
+    pub prg_rom_size: usize,
+    pub chr_rom_size: usize,
     pub prg_ram_pages: u8,
+    pub prg_ram_size: usize,
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
     pub mirroring: Mirroring,
+    /// Flags 6 bit 0, which some boards still read when the four-screen bit is set.
+    #[cfg_attr(feature = "json", serde(skip))]
+    pub vertical_mirroring: bool,
     pub battery: bool,
     pub trainer: bool,
-    pub mapper: u8,
+    pub mapper: u16,
+    pub submapper: u8,
+    pub console_type: ConsoleType,
+    pub timing: Timing,
+    pub expansion_device: u8,
 }
 
 impl Header {
@@ -55,7 +97,6 @@ impl Header {
 
         let battery = flags_6.contains(1);
         let trainer = flags_6.contains(2);
-        let mapper = (flags_7 & 0xF0) | (flags_6 >> 4);
         let is_vertical_mirroring = flags_6.contains(0);
         let is_four_screen = flags_6.contains(3);
 
@@ -65,26 +106,160 @@ impl Header {
             (false, false) => Mirroring::Horizontal,
         };
 
-        Ok(Self {
-            prg_rom_pages,
+        // https://www.nesdev.org/wiki/NES_2.0#Identification
+        let version = match flags_7 & 0x0C {
+            0x08 => Version::Nes2,
+            0x00 if bytes.get(12..16).is_some_and(|b| b == [0; 4]) => Version::INes,
+            _ => Version::Archaic,
+        };
+
+        let mut header = Self {
+            version,
+            prg_rom_pages: prg_rom_pages as u16,
+            chr_rom_pages: chr_rom_pages as u16,
+            prg_rom_size: prg_rom_pages as usize * PRG_ROM_PAGE_SIZE,
+            chr_rom_size: chr_rom_pages as usize * CHR_ROM_PAGE_SIZE,
             prg_ram_pages,
-            chr_rom_pages,
+            prg_ram_size: prg_ram_pages.max(1) as usize * PRG_RAM_SIZE,
+            prg_nvram_size: 0,
+            chr_ram_size: (chr_rom_pages == 0) as usize * CHR_RAM_PAGE_SIZE,
+            chr_nvram_size: 0,
             mirroring,
+            vertical_mirroring: is_vertical_mirroring,
             battery,
             trainer,
-            mapper,
-        })
+            mapper: (flags_6 >> 4) as u16,
+            submapper: 0,
+            console_type: ConsoleType::Nes,
+            timing: Timing::Ntsc,
+            expansion_device: 0,
+        };
+
+        match version {
+            Version::Archaic => {} // upper mapper nibble is usually garbage
+            Version::INes => header.mapper |= (flags_7 & 0xF0) as u16,
+            Version::Nes2 => header.parse_nes2(bytes, *flags_7)?,
+        }
+
+        Ok(header)
+    }
+
+    // https://www.nesdev.org/wiki/NES_2.0
+    fn parse_nes2(&mut self, bytes: &[u8], flags_7: u8) -> Result<(), Error> {
+        let mapper_byte = *bytes.get(8).ok_or(Error::eof("Mapper MSB/Submapper", 1))?;
+        let rom_size_msb = *bytes.get(9).ok_or(Error::eof("PRG/CHR ROM size MSB", 1))?;
+        let prg_ram_shifts = *bytes.get(10).ok_or(Error::eof("PRG RAM shifts", 1))?;
+        let chr_ram_shifts = *bytes.get(11).ok_or(Error::eof("CHR RAM shifts", 1))?;
+        let timing = *bytes.get(12).ok_or(Error::eof("CPU/PPU timing", 1))?;
+        let expansion_device = *bytes.get(15).ok_or(Error::eof("Expansion device", 1))?;
+
+        let (prg_rom_lsb, chr_rom_lsb) = (bytes[4], bytes[5]);
+        let prg_rom_msb = rom_size_msb & 0x0F;
+        let chr_rom_msb = rom_size_msb >> 4;
+
+        self.mapper |= (flags_7 & 0xF0) as u16 | ((mapper_byte & 0x0F) as u16) << 8;
+        self.submapper = mapper_byte >> 4;
+        self.prg_rom_size = get_rom_size(prg_rom_lsb, prg_rom_msb, PRG_ROM_PAGE_SIZE);
+        self.chr_rom_size = get_rom_size(chr_rom_lsb, chr_rom_msb, CHR_ROM_PAGE_SIZE);
+        self.prg_rom_pages = get_rom_pages(self.prg_rom_size, PRG_ROM_PAGE_SIZE, "PRG ROM")?;
+        self.chr_rom_pages = get_rom_pages(self.chr_rom_size, CHR_ROM_PAGE_SIZE, "CHR ROM")?;
+        self.prg_ram_size = get_ram_size(prg_ram_shifts & 0x0F);
+        self.prg_nvram_size = get_ram_size(prg_ram_shifts >> 4);
+        self.prg_ram_pages = (self.prg_ram_size + self.prg_nvram_size)
+            .div_ceil(PRG_RAM_SIZE)
+            .min(u8::MAX as usize) as u8;
+        self.chr_ram_size = get_ram_size(chr_ram_shifts & 0x0F);
+        self.chr_nvram_size = get_ram_size(chr_ram_shifts >> 4);
+        self.expansion_device = expansion_device & 0x3F;
+
+        self.console_type = match flags_7 & 0b11 {
+            0 => ConsoleType::Nes,
+            1 => ConsoleType::VsSystem,
+            2 => ConsoleType::Playchoice10,
+            _ => ConsoleType::Extended,
+        };
+
+        self.timing = match timing & 0b11 {
+            0 => Timing::Ntsc,
+            1 => Timing::Pal,
+            2 => Timing::MultiRegion,
+            _ => Timing::Dendy,
+        };
+
+        Ok(())
+    }
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Self {
+            version: Version::INes,
+            prg_rom_pages: 1,
+            chr_rom_pages: 1,
+            prg_rom_size: PRG_ROM_PAGE_SIZE,
+            chr_rom_size: CHR_ROM_PAGE_SIZE,
+            prg_ram_pages: 0,
+            prg_ram_size: 0,
+            prg_nvram_size: 0,
+            chr_ram_size: 0,
+            chr_nvram_size: 0,
+            mirroring: Mirroring::Vertical,
+            vertical_mirroring: true,
+            battery: false,
+            trainer: false,
+            mapper: 0,
+            submapper: 0,
+            console_type: ConsoleType::Nes,
+            timing: Timing::Ntsc,
+            expansion_device: 0,
+        }
+    }
+}
+
+// https://www.nesdev.org/wiki/NES_2.0#PRG-ROM_Area
+fn get_rom_size(lsb: u8, msb: u8, page_size: usize) -> usize {
+    if msb == 0x0F {
+        let exponent = (lsb >> 2) as u32;
+        let multiplier = (lsb & 0b11) as usize * 2 + 1;
+        2_usize.saturating_pow(exponent).saturating_mul(multiplier)
+    } else {
+        (((msb as usize) << 8) | lsb as usize) * page_size
+    }
+}
+
+fn get_rom_pages(size: usize, page_size: usize, name: &str) -> Result<u16, Error> {
+    u16::try_from(size.div_ceil(page_size))
+        .map_err(|_| Error::InvalidHeader(format!("{name} size of {size} bytes is too large")))
+}
+
+// https://www.nesdev.org/wiki/NES_2.0#PRG-(NV)RAM/EEPROM
+fn get_ram_size(shift: u8) -> usize {
+    match shift {
+        0 => 0,
+        shift => 64 << shift,
     }
 }
 
+fn chr_offset(address: u16, page: ChrPage, size: usize) -> Option<usize> {
+    let (page_start, mask) = match page {
+        ChrPage::Index1(index) => (index * (CHR_ROM_PAGE_SIZE / 8), 0x03FF),
+        ChrPage::Index4(index) => (index * (CHR_ROM_PAGE_SIZE / 2), 0x0FFF),
+        ChrPage::Index8(index) => (index * CHR_ROM_PAGE_SIZE, 0x1FFF),
+    };
+
+    (page_start + (address as usize & mask)).checked_rem(size)
+}
+
 pub enum ChrPage {
-    Index4(u8),
-    Index8(u8),
+    Index1(usize),
+    Index4(usize),
+    Index8(usize),
 }
 
 pub enum PrgPage {
-    Index16(u8),
-    Index32(u8),
+    Index8(usize),
+    Index16(usize),
+    Index32(usize),
     Last16,
 }
 
@@ -94,7 +269,9 @@ pub struct Cartridge {
     pub chr_rom: Vec<u8>,
     pub prg_ram: Vec<u8>,
     pub chr_ram: Vec<u8>,
+    pub vram: Vec<u8>,
     pub observer: Option<Box<dyn MemoryObserver>>,
+    pub(crate) prg_ram_dirty: bool,
 }
 
 impl std::fmt::Debug for Cartridge {
@@ -107,16 +284,29 @@ impl Cartridge {
     pub fn try_from_bytes(bytes: &[u8]) -> Result<Self, Error> {
         let header = Header::try_from_bytes(bytes)?;
 
-        let prg_rom_size = header.prg_rom_pages as usize * PRG_ROM_PAGE_SIZE;
-        let chr_rom_size = header.chr_rom_pages as usize * CHR_ROM_PAGE_SIZE;
+        if header.prg_rom_size == 0 {
+            return Err(Error::EmptyPrgRom);
+        }
+
+        let prg_rom_size = header.prg_rom_size;
+        let chr_rom_size = header.chr_rom_size;
         let prg_rom_start = INES_HEADER_SIZE + if header.trainer { TRAINER_SIZE } else { 0 };
         let chr_rom_start = prg_rom_start + prg_rom_size;
-        let prg_rom = bytes[prg_rom_start..prg_rom_start + prg_rom_size].to_vec();
-        let chr_rom = bytes[chr_rom_start..chr_rom_start + chr_rom_size].to_vec();
-        let prg_ram_size = PRG_RAM_SIZE;
-        let prg_ram = vec![0_u8; prg_ram_size];
-        let chr_ram_size = (header.chr_rom_pages == 0) as usize * CHR_RAM_PAGE_SIZE;
-        let chr_ram = vec![0_u8; chr_ram_size];
+        let prg_rom = bytes
+            .get(prg_rom_start..chr_rom_start)
+            .ok_or(Error::eof("PRG ROM", prg_rom_size))?
+            .to_vec();
+        let chr_rom = bytes
+            .get(chr_rom_start..chr_rom_start + chr_rom_size)
+            .ok_or(Error::eof("CHR ROM", chr_rom_size))?
+            .to_vec();
+        let prg_ram = vec![0_u8; header.prg_ram_size + header.prg_nvram_size];
+        let chr_ram = vec![0_u8; header.chr_ram_size + header.chr_nvram_size];
+        let vram_size = match header.mirroring {
+            Mirroring::FourScreen => FOUR_SCREEN_VRAM_SIZE,
+            _ => 0,
+        };
+        let vram = vec![0_u8; vram_size];
 
         Ok(Self {
             header,
@@ -124,44 +314,98 @@ impl Cartridge {
             chr_rom,
             prg_ram,
             chr_ram,
+            vram,
             observer: None,
+            prg_ram_dirty: false,
         })
     }
 
+    /// CRC-32 of the PRG and CHR ROM, the checksum ROM databases identify games with.
+    pub fn crc32(&self) -> u32 {
+        crc32(self.prg_rom.iter().chain(&self.chr_rom))
+    }
+
     pub fn write_prg_ram(&mut self, address: u16, value: u8) {
-        self.prg_ram[address as usize & 0x1FFF] = value;
+        self.write_prg_ram_page(address, value, 0);
+    }
+
+    /// Writes to an 8 KB PRG RAM page, the page index wraps around the RAM size.
+    pub fn write_prg_ram_page(&mut self, address: u16, value: u8, page: u8) {
+        if let Some(offset) = self.prg_ram_offset(address, page) {
+            self.write_prg_ram_offset(offset, value);
+        }
+    }
+
+    /// Writes to PRG RAM by absolute offset, for mapper memory sharing its battery.
+    pub fn write_prg_ram_offset(&mut self, offset: usize, value: u8) {
+        self.prg_ram[offset] = value;
+        self.prg_ram_dirty = true;
 
         if let Some(observer) = &mut self.observer {
             observer.observe(&self.prg_ram);
         }
     }
 
-    pub fn write_chr_ram(&mut self, address: u16, value: u8, page: ChrPage) {
+    /// Writes to CHR RAM when the board has no CHR ROM, the page index wraps around the RAM size.
+    pub fn write_chr(&mut self, address: u16, value: u8, page: ChrPage) {
         if self.header.chr_rom_pages == 0 {
-            let (page_start, mask) = match page {
-                ChrPage::Index4(index) => (index as usize * (CHR_ROM_PAGE_SIZE / 2), 0x0FFF),
-                ChrPage::Index8(index) => (index as usize * CHR_ROM_PAGE_SIZE, 0x1FFF),
-            };
+            self.write_chr_ram(address, value, page);
+        }
+    }
 
-            self.chr_ram[page_start + (address as usize & mask)] = value;
+    /// Writes to CHR RAM even when the board also has CHR ROM, for mappers banking both.
+    pub fn write_chr_ram(&mut self, address: u16, value: u8, page: ChrPage) {
+        if let Some(offset) = chr_offset(address, page, self.chr_ram.len()) {
+            self.chr_ram[offset] = value;
         }
     }
 
     pub fn read_prg_rom(&self, address: u16, page: PrgPage) -> u8 {
         let (page_start, mask) = match page {
-            PrgPage::Index16(index) => (index as usize * PRG_ROM_PAGE_SIZE, 0x3FFF),
-            PrgPage::Index32(index) => (index as usize * PRG_ROM_PAGE_SIZE * 2, 0x7FFF),
+            PrgPage::Index8(index) => (index * (PRG_ROM_PAGE_SIZE / 2), 0x1FFF),
+            PrgPage::Index16(index) => (index * PRG_ROM_PAGE_SIZE, 0x3FFF),
+            PrgPage::Index32(index) => (index * PRG_ROM_PAGE_SIZE * 2, 0x7FFF),
             PrgPage::Last16 => (
-                (self.header.prg_rom_pages as usize - 1) * PRG_ROM_PAGE_SIZE,
+                (self.header.prg_rom_pages as usize).saturating_sub(1) * PRG_ROM_PAGE_SIZE,
                 0x3FFF,
             ),
         };
 
-        self.prg_rom[page_start + (address as usize & mask)]
+        // banks past the end of odd-sized ROMs wrap around
+        (page_start + (address as usize & mask))
+            .checked_rem(self.prg_rom.len())
+            .map_or(0, |offset| self.prg_rom[offset])
     }
 
     pub fn read_prg_ram(&self, address: u16) -> u8 {
-        self.prg_ram[address as usize & 0x1FFF]
+        self.read_prg_ram_page(address, 0)
+    }
+
+    pub fn read_prg_ram_page(&self, address: u16, page: u8) -> u8 {
+        self.prg_ram_offset(address, page)
+            .map_or(0, |offset| self.prg_ram[offset])
+    }
+
+    fn prg_ram_offset(&self, address: u16, page: u8) -> Option<usize> {
+        let offset = page as usize * PRG_RAM_SIZE + (address as usize & 0x1FFF);
+        offset.checked_rem(self.prg_ram.len())
+    }
+
+    pub fn battery_ram(&self) -> Option<&[u8]> {
+        self.header.battery.then_some(self.prg_ram.as_slice())
+    }
+
+    pub fn load_battery_ram(&mut self, bytes: &[u8]) {
+        if self.header.battery {
+            let length = bytes.len().min(self.prg_ram.len());
+            self.prg_ram[..length].copy_from_slice(&bytes[..length]);
+            self.prg_ram_dirty = false;
+        }
+    }
+
+    /// Returns whether the PRG RAM was written since the last call.
+    pub fn take_prg_ram_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.prg_ram_dirty)
     }
 
     pub fn read_chr(&self, address: u16, page: ChrPage) -> u8 {
@@ -169,41 +413,122 @@ impl Cartridge {
             0 => &self.chr_ram,
             _ => &self.chr_rom,
         };
-        let (page_start, mask) = match page {
-            ChrPage::Index4(index) => (index as usize * (CHR_ROM_PAGE_SIZE / 2), 0x0FFF),
-            ChrPage::Index8(index) => (index as usize * CHR_ROM_PAGE_SIZE, 0x1FFF),
-        };
 
-        chr[page_start + (address as usize & mask)]
+        chr_offset(address, page, chr.len()).map_or(0, |offset| chr[offset])
+    }
+
+    /// Reads CHR RAM even when the board also has CHR ROM, for mappers banking both.
+    pub fn read_chr_ram(&self, address: u16, page: ChrPage) -> u8 {
+        chr_offset(address, page, self.chr_ram.len()).map_or(0, |offset| self.chr_ram[offset])
+    }
+
+    /// Reads the nametable RAM on the board, mirrored over $2000-$2FFF.
+    pub fn read_vram(&self, address: u16) -> u8 {
+        self.vram_offset(address)
+            .map_or(0, |offset| self.vram[offset])
+    }
+
+    pub fn write_vram(&mut self, address: u16, value: u8) {
+        if let Some(offset) = self.vram_offset(address) {
+            self.vram[offset] = value;
+        }
+    }
+
+    fn vram_offset(&self, address: u16) -> Option<usize> {
+        (address as usize & 0x0FFF).checked_rem(self.vram.len())
     }
 }
 
 impl Default for Cartridge {
     fn default() -> Self {
         Self {
-            header: Header {
-                prg_rom_pages: 1,
-                chr_rom_pages: 1,
-                prg_ram_pages: 0,
-                mirroring: Mirroring::Vertical,
-                battery: false,
-                trainer: false,
-                mapper: 0,
-            },
+            header: Header::default(),
             prg_rom: vec![0; PRG_ROM_PAGE_SIZE],
             chr_rom: vec![0; CHR_ROM_PAGE_SIZE],
             prg_ram: vec![],
             chr_ram: vec![],
+            vram: vec![],
             observer: None,
+            prg_ram_dirty: false,
+        }
+    }
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+            bit += 1;
         }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+};
+
+fn crc32<'a>(bytes: impl Iterator<Item = &'a u8>) -> u32 {
+    !bytes.fold(!0_u32, |crc, &byte| {
+        CRC32_TABLE[(crc as u8 ^ byte) as usize] ^ (crc >> 8)
+    })
+}
+
+impl Snapshot for Mirroring {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(match self {
+            Mirroring::Vertical => 0,
+            Mirroring::Horizontal => 1,
+            Mirroring::OneScreenLower => 2,
+            Mirroring::FourScreen => 3,
+            Mirroring::OneScreenUpper => 4,
+        });
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        *self = match state.read_u8()? {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::OneScreenLower,
+            3 => Mirroring::FourScreen,
+            4 => Mirroring::OneScreenUpper,
+            _ => return Err(Error::InvalidSaveState),
+        };
+
+        Ok(())
+    }
+}
+
+impl Snapshot for Cartridge {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_bytes(&self.prg_ram);
+        state.write_bytes(&self.chr_ram);
+        state.write_bytes(&self.vram);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        state.read_bytes(&mut self.prg_ram)?;
+        state.read_bytes(&mut self.chr_ram)?;
+        state.read_bytes(&mut self.vram)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::utils::test::NESTEST_ROM;
+    use crate::{
+        error::Error,
+        utils::test::{XorShift, NESTEST_ROM},
+    };
 
-    use super::Cartridge;
+    use super::{
+        crc32, Cartridge, ChrPage, ConsoleType, Header, Mirroring, PrgPage, Timing, Version,
+    };
 
     #[test]
     fn test_load_rom() {
@@ -211,4 +536,207 @@ mod tests {
 
         assert!(rom.is_ok());
     }
+
+    #[test]
+    fn test_ram_sizes() {
+        let mut bytes = vec![
+            b'N', b'E', b'S', 0x1A, 1, 0, 0x00, 0x08, 0, 0, 0x00, 0x09, 0, 0, 0, 0,
+        ];
+        bytes.resize(16 + 16384, 0);
+
+        let mut cartridge = Cartridge::try_from_bytes(&bytes).unwrap();
+
+        assert!(cartridge.prg_ram.is_empty());
+        assert_eq!(cartridge.chr_ram.len(), 32768);
+        assert_eq!(cartridge.read_prg_ram(0x6000), 0);
+
+        cartridge.write_chr(0x0010, 0x42, ChrPage::Index8(3));
+        cartridge.write_chr(0x0020, 0x24, ChrPage::Index8(4));
+
+        assert_eq!(cartridge.chr_ram[3 * 8192 + 0x10], 0x42);
+        assert_eq!(cartridge.read_chr(0x0010, ChrPage::Index4(6)), 0x42);
+        assert_eq!(cartridge.read_chr(0x0020, ChrPage::Index8(0)), 0x24);
+    }
+
+    #[test]
+    fn test_chr_rom_and_ram() {
+        let mut bytes = vec![
+            b'N', b'E', b'S', 0x1A, 1, 1, 0x00, 0x08, 0, 0, 0x00, 0x07, 0, 0, 0, 0,
+        ];
+        bytes.resize(16 + 16384, 0);
+        bytes.resize(16 + 16384 + 8192, 0xAA);
+
+        let mut cartridge = Cartridge::try_from_bytes(&bytes).unwrap();
+
+        assert_eq!(cartridge.chr_ram.len(), 8192);
+
+        cartridge.write_chr(0x0010, 0x42, ChrPage::Index8(0));
+        cartridge.write_chr_ram(0x0020, 0x24, ChrPage::Index1(1));
+
+        assert_eq!(cartridge.read_chr(0x0010, ChrPage::Index8(0)), 0xAA);
+        assert_eq!(cartridge.read_chr(0x0420, ChrPage::Index8(0)), 0xAA);
+        assert_eq!(cartridge.read_chr_ram(0x0010, ChrPage::Index8(0)), 0);
+        assert_eq!(cartridge.read_chr_ram(0x0420, ChrPage::Index8(0)), 0x24);
+    }
+
+    #[test]
+    fn test_ines_header() {
+        let bytes = [
+            b'N', b'E', b'S', 0x1A, 2, 1, 0x49, 0x10, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        let header = Header::try_from_bytes(&bytes).unwrap();
+
+        assert_eq!(header.version, Version::INes);
+        assert_eq!(header.mapper, 0x14);
+        assert_eq!(header.prg_rom_size, 32768);
+        assert_eq!(header.prg_ram_size, 8192);
+        assert_eq!(header.chr_ram_size, 0);
+        assert_eq!(header.mirroring, Mirroring::FourScreen);
+        assert!(header.vertical_mirroring);
+    }
+
+    #[test]
+    fn test_archaic_header() {
+        let bytes = b"NES\x1A\x02\x01\x41\x44iskDude!";
+        let header = Header::try_from_bytes(bytes).unwrap();
+
+        assert_eq!(header.version, Version::Archaic);
+        assert_eq!(header.mapper, 0x04);
+    }
+
+    #[test]
+    fn test_nes2_header() {
+        let bytes = [
+            b'N', b'E', b'S', 0x1A, 0x02, 0x00, 0x02, 0x19, 0x21, 0x10, 0x77, 0x07, 0x01, 0, 0,
+            0x41,
+        ];
+        let header = Header::try_from_bytes(&bytes).unwrap();
+
+        assert_eq!(header.version, Version::Nes2);
+        assert_eq!(header.mapper, 0x110);
+        assert_eq!(header.submapper, 2);
+        assert_eq!(header.prg_rom_pages, 2);
+        assert_eq!(header.chr_rom_pages, 256);
+        assert_eq!(header.chr_rom_size, 256 * 8192);
+        assert_eq!(header.prg_ram_size, 8192);
+        assert_eq!(header.prg_nvram_size, 8192);
+        assert_eq!(header.chr_ram_size, 8192);
+        assert_eq!(header.chr_nvram_size, 0);
+        assert_eq!(header.console_type, ConsoleType::VsSystem);
+        assert_eq!(header.timing, Timing::Pal);
+        assert_eq!(header.expansion_device, 0x01);
+        assert!(header.battery);
+    }
+
+    #[test]
+    fn test_nes2_exponent_rom_size() {
+        let bytes = [
+            b'N',
+            b'E',
+            b'S',
+            0x1A,
+            0b0100_1001,
+            0,
+            0,
+            0x08,
+            0,
+            0x0F,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ];
+        let header = Header::try_from_bytes(&bytes).unwrap();
+
+        assert_eq!(header.prg_rom_size, (1 << 18) * 3);
+        assert_eq!(header.prg_rom_pages, 48);
+    }
+
+    #[test]
+    fn test_unknown_header_version() {
+        let bytes = [
+            b'N', b'E', b'S', 0x1A, 1, 1, 0x10, 0xDC, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        let header = Header::try_from_bytes(&bytes).unwrap();
+
+        assert_eq!(header.version, Version::Archaic);
+        assert_eq!(header.mapper, 1);
+    }
+
+    #[test]
+    fn test_malformed_input() {
+        let ines = |prg: u8, chr: u8, flags_7: u8, size_msb: u8| {
+            vec![
+                b'N', b'E', b'S', 0x1A, prg, chr, 0, flags_7, 0, size_msb, 0, 0, 0, 0, 0, 0,
+            ]
+        };
+
+        assert_eq!(
+            Cartridge::try_from_bytes(&[]).unwrap_err(),
+            Error::UnsupportedFileFormat
+        );
+        assert_eq!(
+            Cartridge::try_from_bytes(b"NES").unwrap_err(),
+            Error::UnsupportedFileFormat
+        );
+        assert_eq!(
+            Cartridge::try_from_bytes(&ines(0, 1, 0, 0)).unwrap_err(),
+            Error::EmptyPrgRom
+        );
+        assert_eq!(
+            Cartridge::try_from_bytes(&ines(2, 1, 0, 0)).unwrap_err(),
+            Error::eof("PRG ROM", 32768)
+        );
+        assert!(matches!(
+            Cartridge::try_from_bytes(&ines(0xFF, 0, 0x08, 0x0F)),
+            Err(Error::InvalidHeader(_))
+        ));
+        assert_eq!(
+            Cartridge::try_from_bytes(&ines(0, 1, 0x08, 0x01)).unwrap_err(),
+            Error::eof("PRG ROM", 256 * 16384)
+        );
+
+        let mut bytes = ines(1, 1, 0, 0);
+        bytes.resize(16 + 16384 + 100, 0);
+
+        assert_eq!(
+            Cartridge::try_from_bytes(&bytes).unwrap_err(),
+            Error::eof("CHR ROM", 8192)
+        );
+
+        // odd NES 2.0 sizes smaller than a page
+        let mut bytes = ines(0b0000_0001, 0, 0x08, 0x0F);
+        bytes.resize(16 + 3, 0xEA);
+        let cartridge = Cartridge::try_from_bytes(&bytes).unwrap();
+
+        assert_eq!(cartridge.read_prg_rom(0xFFFC, PrgPage::Last16), 0xEA);
+    }
+
+    #[test]
+    fn test_random_input() {
+        let mut rng = XorShift::new(0x6E65_735F_6675_7A7A);
+
+        for _ in 0..10_000 {
+            let length = rng.below(64);
+            let mut bytes = rng.bytes(length);
+
+            if rng.below(4) != 0 && length >= 4 {
+                bytes[..4].copy_from_slice(b"NES\x1A");
+            }
+
+            if let Ok(cartridge) = Cartridge::try_from_bytes(&bytes) {
+                cartridge.read_prg_rom(0x8000, PrgPage::Index32(rng.below(1024)));
+                cartridge.read_prg_rom(0xFFFF, PrgPage::Last16);
+                cartridge.read_chr(rng.next_u16(), ChrPage::Index1(rng.below(1024)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789".iter()), 0xCBF4_3926);
+        assert_eq!(crc32([].iter()), 0);
+    }
 }
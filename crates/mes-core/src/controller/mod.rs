@@ -1,4 +1,7 @@
-use crate::utils::BitFlag;
+use crate::{
+    error::Error,
+    utils::{BitFlag, Snapshot, SnapshotReader, SnapshotWriter},
+};
 
 #[derive(Debug, Default)]
 pub struct ControllerState {
@@ -15,9 +18,25 @@ impl ControllerState {
         self.shift = self.state;
     }
 
+    pub fn peek_button(&self, id: usize) -> u8 {
+        self.shift[id].get(7)
+    }
+
     pub fn poll_button(&mut self, id: usize) -> u8 {
-        let value = self.shift[id].get(7);
+        let value = self.peek_button(id);
         self.shift[id] <<= 1;
         value
     }
 }
+
+impl Snapshot for ControllerState {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_bytes(&self.state);
+        state.write_bytes(&self.shift);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        state.read_bytes(&mut self.state)?;
+        state.read_bytes(&mut self.shift)
+    }
+}
@@ -1,5 +1,7 @@
 // https://www.nesdev.org/wiki/CPU_interrupts
 
+use crate::error::Error;
+
 pub const INTERRUPT_LATENCY: u8 = 7;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -18,3 +20,26 @@ impl Interrupt {
         }
     }
 }
+
+impl From<Interrupt> for u8 {
+    fn from(value: Interrupt) -> Self {
+        match value {
+            Interrupt::Nmi => 0,
+            Interrupt::Reset => 1,
+            Interrupt::Irq => 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for Interrupt {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Interrupt::Nmi),
+            1 => Ok(Interrupt::Reset),
+            2 => Ok(Interrupt::Irq),
+            _ => Err(Error::InvalidSaveState),
+        }
+    }
+}
@@ -1,8 +1,8 @@
 // https://www.masswerk.at/6502/6502_instruction_set.html
 
-mod address;
-mod opcodes;
-mod register;
+pub(crate) mod address;
+pub(crate) mod opcodes;
+pub(crate) mod register;
 
 pub mod interrupt;
 
@@ -16,7 +16,9 @@ use crate::{
         interrupt::{Interrupt, INTERRUPT_LATENCY},
         register::{status_flag, CpuRegister, StatusRegister},
     },
-    utils::{BitFlag, Clock, Reset},
+    debugger::{Instruction, Registers},
+    error::Error,
+    utils::{BitFlag, Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
 };
 
 use std::{
@@ -75,10 +77,7 @@ impl Cpu {
     }
 
     pub fn cycle(&mut self) -> u8 {
-        self.interrupt = self
-            .interrupt
-            .or_else(|| self.bus.poll_interrupt())
-            .or_else(|| self.apu.borrow().poll_irq());
+        self.interrupt = self.interrupt.or_else(|| self.bus.poll_interrupt());
 
         if let Some(interrupt) = self.interrupt.take() {
             if self.handle_interrupt(interrupt) {
@@ -108,12 +107,44 @@ impl Cpu {
             return if end { 2 } else { 1 };
         }
 
+        let opcode = self.bus.peek_u8(self.pc);
+
+        if self.bus.debugger.on_instruction(self.registers(), opcode) {
+            return 0; // halted on an execution breakpoint
+        }
+
+        if self.bus.debugger.is_tracing() {
+            let instruction = self.disassemble(self.pc);
+            let (scanline, dot) = (self.bus.ppu.get_scanline(), self.bus.ppu.get_dot());
+            self.bus.debugger.trace(&instruction, scanline, dot);
+        }
+
         let opcode = self.bus.read_u8(self.pc);
 
         self.increment_pc(1);
         self.execute(opcode)
     }
 
+    pub fn registers(&self) -> Registers {
+        Registers {
+            pc: self.pc,
+            a: self.ac,
+            x: self.x,
+            y: self.y,
+            p: self.sr.value(),
+            sp: self.sp,
+            cycle: self.cycle,
+        }
+    }
+
+    /// Disassembles the instruction at `address` without side effects.
+    pub fn disassemble(&self, address: u16) -> Instruction {
+        let read = |address| self.bus.peek_u8(address);
+        let mut instruction = Instruction::decode(address, read);
+        instruction.resolve(&self.registers(), read);
+        instruction
+    }
+
     pub fn execute(&mut self, opcode: u8) -> u8 {
         let opcode = OPCODES[opcode as usize];
         let adr_mode = opcode.adr_mode;
@@ -296,6 +327,15 @@ impl Cpu {
         }
     }
 
+    /// Read-modify-write instructions write the unmodified value back before the result.
+    fn modify_address(&mut self, address: Address, operand: u8, result: u8) {
+        if let Address::Memory(adr) = address {
+            self.bus.dummy_write_u8(adr, operand);
+        }
+
+        self.write_address(address, result);
+    }
+
     fn read_register(&self, register: CpuRegister) -> u8 {
         match register {
             CpuRegister::AC => self.ac,
@@ -462,7 +502,7 @@ impl Cpu {
         self.sr.update(status_flag::C, operand >> 7 == 1);
         self.sr.update_negative(result);
         self.sr.update_zero(result);
-        self.write_address(address, result);
+        self.modify_address(address, operand, result);
     }
 
     fn lsr(&mut self, address: Address) {
@@ -471,7 +511,7 @@ impl Cpu {
         self.sr.update(status_flag::C, operand << 7 == 128);
         self.sr.update_negative(result);
         self.sr.update_zero(result);
-        self.write_address(address, result);
+        self.modify_address(address, operand, result);
     }
 
     fn rol(&mut self, address: Address) {
@@ -481,7 +521,7 @@ impl Cpu {
         self.sr.update(status_flag::C, operand >> 7 == 1);
         self.sr.update_negative(result);
         self.sr.update_zero(result);
-        self.write_address(address, result);
+        self.modify_address(address, operand, result);
     }
 
     fn ror(&mut self, address: Address) {
@@ -491,7 +531,7 @@ impl Cpu {
         self.sr.update(status_flag::C, operand & 1 == 1);
         self.sr.update_negative(result);
         self.sr.update_zero(result);
-        self.write_address(address, result);
+        self.modify_address(address, operand, result);
     }
 
     fn clc(&mut self) {
@@ -697,17 +737,19 @@ impl Cpu {
     }
 
     fn decrement(&mut self, address: Address) {
-        let result = self.read_address(address).wrapping_sub(1);
+        let operand = self.read_address(address);
+        let result = operand.wrapping_sub(1);
         self.sr.update_negative(result);
         self.sr.update_zero(result);
-        self.write_address(address, result);
+        self.modify_address(address, operand, result);
     }
 
     fn increment(&mut self, address: Address) {
-        let result = self.read_address(address).wrapping_add(1);
+        let operand = self.read_address(address);
+        let result = operand.wrapping_add(1);
         self.sr.update_negative(result);
         self.sr.update_zero(result);
-        self.write_address(address, result);
+        self.modify_address(address, operand, result);
     }
 
     fn add_to_accumulator(&mut self, value: u8) {
@@ -807,14 +849,71 @@ impl Reset for Cpu {
     }
 }
 
+impl Snapshot for Cpu {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u16(self.pc);
+        state.write_u8(self.ac);
+        state.write_u8(self.x);
+        state.write_u8(self.y);
+        state.write_u8(self.sr.value());
+        state.write_u8(self.sp);
+        state.write_u64(self.cycle);
+        state.write_option_u8(self.interrupt.map(u8::from));
+        state.write_bool(self.dma.is_some());
+
+        if let Some(dma) = &self.dma {
+            dma.save(state);
+        }
+
+        self.bus.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.pc = state.read_u16()?;
+        self.ac = state.read_u8()?;
+        self.x = state.read_u8()?;
+        self.y = state.read_u8()?;
+        self.sr.assign(state.read_u8()?);
+        self.sp = state.read_u8()?;
+        self.cycle = state.read_u64()?;
+        self.interrupt = state
+            .read_option_u8()?
+            .map(Interrupt::try_from)
+            .transpose()?;
+        self.dma = match state.read_bool()? {
+            true => {
+                let mut dma = DmaState::new(0);
+                dma.load(state)?;
+                Some(dma)
+            }
+            false => None,
+        };
+
+        self.bus.load(state)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
         bus::{Bus, MainBus},
         cpu::Cpu,
-        mappers::MapperChip,
-        utils::test::{LogLine, NESTEST_LOG, NESTEST_ROM},
+        debugger::{Break, Breakpoint, BreakpointKind},
+        mappers::{MapperBuilder, MapperChip},
+        utils::{
+            test::{nrom, LogLine, NESTEST_LOG, NESTEST_ROM},
+            MemoryObserver,
+        },
     };
+    use std::{cell::RefCell, rc::Rc};
+
+    struct WriteLog(Rc<RefCell<Vec<u8>>>);
+
+    impl MemoryObserver for WriteLog {
+        fn observe(&mut self, bytes: &[u8]) {
+            self.0.borrow_mut().push(bytes[0]);
+        }
+    }
 
     #[test]
     fn test_cpu_nestest() {
@@ -848,4 +947,30 @@ mod tests {
             cpu.step();
         }
     }
+
+    #[test]
+    fn test_cpu_read_modify_write() {
+        let writes = Rc::new(RefCell::new(Vec::new()));
+        let rom = nrom(&[0xEE, 0x00, 0x60]); // INC $6000
+        let mapper = MapperBuilder::new(&rom)
+            .unwrap()
+            .with_observer(WriteLog(writes.clone()))
+            .build()
+            .unwrap();
+        let mut cpu = Cpu::new(MainBus::new(mapper));
+
+        cpu.bus.debugger.add_breakpoint(Breakpoint::write(0x6000));
+        cpu.step(); // reset interrupt
+        cpu.step();
+
+        assert_eq!(*writes.borrow(), [0x00, 0x01]);
+        assert_eq!(
+            cpu.bus.debugger.take_break(),
+            Some(Break {
+                kind: BreakpointKind::Write,
+                address: 0x6000,
+                value: 0x01,
+            })
+        );
+    }
 }
@@ -0,0 +1,263 @@
+// https://www.masswerk.at/6502/6502_instruction_set.html#modes
+
+use super::Registers;
+use crate::cpu::{
+    address::AddressMode,
+    opcodes::{Asm, Opcode, OPCODES},
+    register::CpuRegister,
+};
+
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub unofficial: bool,
+    pub effective_address: Option<u16>,
+    pub value: Option<u8>,
+    pointer: Option<u16>,
+    opcode: Opcode,
+}
+
+impl Instruction {
+    /// Decodes the instruction at `address`, `read` must not have side effects.
+    pub fn decode(address: u16, read: impl Fn(u16) -> u8) -> Self {
+        let code = read(address);
+        let opcode = OPCODES[code as usize];
+        let bytes = (0..opcode.len() as u16)
+            .map(|offset| read(address.wrapping_add(offset)))
+            .collect();
+
+        let mut instruction = Self {
+            address,
+            bytes,
+            unofficial: is_unofficial(code, &opcode),
+            effective_address: None,
+            value: None,
+            pointer: None,
+            opcode,
+        };
+
+        instruction.effective_address = match opcode.adr_mode {
+            AddressMode::Absolute if instruction.is_jump() => Some(instruction.operand_u16()),
+            AddressMode::Relative => {
+                let next = address.wrapping_add(2);
+                Some(next.wrapping_add_signed(instruction.operand_u8() as i8 as i16))
+            }
+            _ => None,
+        };
+
+        instruction
+    }
+
+    /// Resolves the memory operand using the given CPU state.
+    pub fn resolve(&mut self, registers: &Registers, read: impl Fn(u16) -> u8) {
+        let read_u16 = |low: u16, high: u16| u16::from_le_bytes([read(low), read(high)]);
+        let operand = self.operand_u8();
+
+        let address = match self.opcode.adr_mode {
+            AddressMode::ZeroPage => operand as u16,
+            AddressMode::ZeroPageX => operand.wrapping_add(registers.x) as u16,
+            AddressMode::ZeroPageY => operand.wrapping_add(registers.y) as u16,
+            AddressMode::Absolute if !self.is_jump() => self.operand_u16(),
+            AddressMode::AbsoluteX => self.operand_u16().wrapping_add(registers.x as u16),
+            AddressMode::AbsoluteY => self.operand_u16().wrapping_add(registers.y as u16),
+            AddressMode::Indirect => {
+                let pointer = self.operand_u16();
+                let high = (pointer & 0xFF00) | (pointer.wrapping_add(1) & 0x00FF);
+                self.effective_address = Some(read_u16(pointer, high));
+                return;
+            }
+            AddressMode::IndirectX => {
+                let pointer = operand.wrapping_add(registers.x);
+                self.pointer = Some(pointer as u16);
+                read_u16(pointer as u16, pointer.wrapping_add(1) as u16)
+            }
+            AddressMode::IndirectY => {
+                let base = read_u16(operand as u16, operand.wrapping_add(1) as u16);
+                self.pointer = Some(base);
+                base.wrapping_add(registers.y as u16)
+            }
+            _ => return,
+        };
+
+        self.effective_address = Some(address);
+        self.value = Some(read(address));
+    }
+
+    pub fn mnemonic(&self) -> String {
+        format!("{:?}", self.opcode.asm)
+    }
+
+    fn is_jump(&self) -> bool {
+        matches!(self.opcode.asm, Asm::JMP | Asm::JSR)
+    }
+
+    fn operand_u8(&self) -> u8 {
+        self.bytes.get(1).copied().unwrap_or_default()
+    }
+
+    fn operand_u16(&self) -> u16 {
+        u16::from_le_bytes([
+            self.operand_u8(),
+            self.bytes.get(2).copied().unwrap_or_default(),
+        ])
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let byte = self.operand_u8();
+        let word = self.operand_u16();
+
+        match self.opcode.adr_mode {
+            AddressMode::Implied(CpuRegister::AC) if self.is_accumulator() => write!(f, " A"),
+            AddressMode::Implied(_) => Ok(()),
+            AddressMode::Immediate => write!(f, " #${byte:02X}"),
+            AddressMode::ZeroPage => write!(f, " ${byte:02X}"),
+            AddressMode::ZeroPageX => write!(f, " ${byte:02X},X"),
+            AddressMode::ZeroPageY => write!(f, " ${byte:02X},Y"),
+            AddressMode::Absolute => write!(f, " ${word:04X}"),
+            AddressMode::AbsoluteX => write!(f, " ${word:04X},X"),
+            AddressMode::AbsoluteY => write!(f, " ${word:04X},Y"),
+            AddressMode::Indirect => write!(f, " (${word:04X})"),
+            AddressMode::IndirectX => write!(f, " (${byte:02X},X)"),
+            AddressMode::IndirectY => write!(f, " (${byte:02X}),Y"),
+            AddressMode::Relative => write!(f, " ${:04X}", self.effective_address.unwrap_or(0)),
+        }
+    }
+
+    fn fmt_resolved(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(address) = self.effective_address else {
+            return Ok(());
+        };
+
+        match (self.opcode.adr_mode, self.pointer, self.value) {
+            (AddressMode::Indirect, _, _) => write!(f, " = {address:04X}"),
+            (_, _, None) => Ok(()),
+            (AddressMode::ZeroPageX | AddressMode::ZeroPageY, _, Some(value)) => {
+                write!(f, " @ {address:02X} = {value:02X}")
+            }
+            (AddressMode::AbsoluteX | AddressMode::AbsoluteY, _, Some(value)) => {
+                write!(f, " @ {address:04X} = {value:02X}")
+            }
+            (AddressMode::IndirectX, Some(pointer), Some(value)) => {
+                write!(f, " @ {pointer:02X} = {address:04X} = {value:02X}")
+            }
+            (AddressMode::IndirectY, Some(pointer), Some(value)) => {
+                write!(f, " = {pointer:04X} @ {address:04X} = {value:02X}")
+            }
+            (_, _, Some(value)) => write!(f, " = {value:02X}"),
+        }
+    }
+
+    fn is_accumulator(&self) -> bool {
+        matches!(self.opcode.asm, Asm::ASL | Asm::LSR | Asm::ROL | Asm::ROR)
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.unofficial {
+            write!(f, "*")?;
+        }
+
+        write!(f, "{:?}", self.opcode.asm)?;
+        self.fmt_operand(f)?;
+        self.fmt_resolved(f)
+    }
+}
+
+/// Disassembles `bytes` loaded at `origin`, an incomplete trailing instruction is skipped.
+pub fn disassemble(bytes: &[u8], origin: u16) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+
+    while let Some(&code) = bytes.get(offset) {
+        let len = OPCODES[code as usize].len() as usize;
+
+        if offset + len > bytes.len() {
+            break;
+        }
+
+        let address = origin.wrapping_add(offset as u16);
+        let read = |adr: u16| bytes[offset + adr.wrapping_sub(address) as usize];
+
+        instructions.push(Instruction::decode(address, read));
+        offset += len;
+    }
+
+    instructions
+}
+
+fn is_unofficial(code: u8, opcode: &Opcode) -> bool {
+    match opcode.asm {
+        Asm::NOP => code != 0xEA,
+        Asm::SBC => code == 0xEB,
+        Asm::ALR
+        | Asm::ANC
+        | Asm::ARR
+        | Asm::LAS
+        | Asm::LAX
+        | Asm::SBX
+        | Asm::DCP
+        | Asm::ISB
+        | Asm::SLO
+        | Asm::RLA
+        | Asm::SRE
+        | Asm::RRA
+        | Asm::SAX
+        | Asm::JAM
+        | Asm::TAS
+        | Asm::SHA
+        | Asm::SHX
+        | Asm::SHY
+        | Asm::ANE
+        | Asm::LXA => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{disassemble, Instruction};
+    use crate::debugger::Registers;
+
+    #[test]
+    fn test_disassemble_bytes() {
+        #[rustfmt::skip]
+        let bytes = [
+            0xBD, 0x00, 0x02, // LDA $0200,X
+            0x4A,             // LSR A
+            0xD0, 0xFA,       // BNE $8000
+            0x04, 0x10,       // *NOP $10
+            0xA9,             // truncated
+        ];
+
+        let lines = disassemble(&bytes, 0x8000)
+            .iter()
+            .map(|instruction| instruction.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(lines, ["LDA $0200,X", "LSR A", "BNE $8000", "*NOP $10"]);
+    }
+
+    #[test]
+    fn test_resolve_effective_address() {
+        let mut memory = [0; 0x800];
+        memory[..2].copy_from_slice(&[0xB1, 0x80]); // LDA ($80),Y
+        memory[0x80..0x82].copy_from_slice(&[0x00, 0x03]);
+        memory[0x305] = 0x89;
+
+        let read = |address: u16| memory[address as usize & 0x7FF];
+        let registers = Registers {
+            y: 5,
+            ..Default::default()
+        };
+
+        let mut instruction = Instruction::decode(0x0000, read);
+        instruction.resolve(&registers, read);
+
+        assert_eq!(instruction.effective_address, Some(0x0305));
+        assert_eq!(instruction.to_string(), "LDA ($80),Y = 0300 @ 0305 = 89");
+    }
+}
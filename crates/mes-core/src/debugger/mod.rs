@@ -0,0 +1,263 @@
+mod disassembler;
+mod trace;
+
+pub use disassembler::{disassemble, Instruction};
+pub use trace::{format_trace, TraceLogger};
+
+/// CPU registers at the start of the current instruction.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Registers {
+    pub pc: u16,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub p: u8,
+    pub sp: u8,
+    pub cycle: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    A,
+    X,
+    Y,
+    P,
+    SP,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+    pub register: Register,
+    pub comparison: Comparison,
+    pub value: u8,
+}
+
+impl Condition {
+    pub fn new(register: Register, comparison: Comparison, value: u8) -> Self {
+        Self {
+            register,
+            comparison,
+            value,
+        }
+    }
+
+    pub fn matches(&self, registers: &Registers) -> bool {
+        let register = match self.register {
+            Register::A => registers.a,
+            Register::X => registers.x,
+            Register::Y => registers.y,
+            Register::P => registers.p,
+            Register::SP => registers.sp,
+        };
+
+        match self.comparison {
+            Comparison::Equal => register == self.value,
+            Comparison::NotEqual => register != self.value,
+            Comparison::Less => register < self.value,
+            Comparison::Greater => register > self.value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakpointKind {
+    Execute,
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub kind: BreakpointKind,
+    pub address: u16,
+    pub condition: Option<Condition>,
+}
+
+impl Breakpoint {
+    pub fn execute(address: u16) -> Self {
+        Self::new(BreakpointKind::Execute, address)
+    }
+
+    pub fn read(address: u16) -> Self {
+        Self::new(BreakpointKind::Read, address)
+    }
+
+    pub fn write(address: u16) -> Self {
+        Self::new(BreakpointKind::Write, address)
+    }
+
+    pub fn with_condition(mut self, condition: Condition) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+
+    fn new(kind: BreakpointKind, address: u16) -> Self {
+        Self {
+            kind,
+            address,
+            condition: None,
+        }
+    }
+
+    fn matches(&self, kind: BreakpointKind, address: u16, registers: &Registers) -> bool {
+        self.kind == kind
+            && self.address == address
+            && self.condition.is_none_or(|cond| cond.matches(registers))
+    }
+}
+
+/// A triggered breakpoint, `value` is the opcode for execution breakpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Break {
+    pub kind: BreakpointKind,
+    pub address: u16,
+    pub value: u8,
+}
+
+#[derive(Debug, Default)]
+pub struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    registers: Registers,
+    instructions: u64,
+    hit: Option<Break>,
+    resume: Option<u16>,
+    logger: Option<TraceLogger>,
+}
+
+impl Debugger {
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    pub fn remove_breakpoint(&mut self, breakpoint: &Breakpoint) {
+        self.breakpoints.retain(|bp| bp != breakpoint);
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    pub fn instruction_count(&self) -> u64 {
+        self.instructions
+    }
+
+    pub fn take_break(&mut self) -> Option<Break> {
+        self.hit.take()
+    }
+
+    pub fn set_trace_logger(&mut self, logger: TraceLogger) {
+        self.logger = Some(logger);
+    }
+
+    pub fn remove_trace_logger(&mut self) -> Option<TraceLogger> {
+        self.logger.take()
+    }
+
+    pub(crate) fn is_tracing(&self) -> bool {
+        self.logger.is_some()
+    }
+
+    pub(crate) fn trace(&mut self, instruction: &Instruction, scanline: u16, dot: u16) {
+        if let Some(logger) = &mut self.logger {
+            logger.log(&format_trace(instruction, &self.registers, scanline, dot));
+        }
+    }
+
+    /// Called before each opcode fetch, returns true if the CPU should halt.
+    pub(crate) fn on_instruction(&mut self, registers: Registers, opcode: u8) -> bool {
+        self.registers = registers;
+
+        if self.resume.take() != Some(registers.pc)
+            && self.check(BreakpointKind::Execute, registers.pc, opcode)
+        {
+            self.resume = Some(registers.pc);
+            return true;
+        }
+
+        self.instructions += 1;
+
+        false
+    }
+
+    pub(crate) fn on_read(&mut self, address: u16, value: u8) {
+        self.check(BreakpointKind::Read, address, value);
+    }
+
+    pub(crate) fn on_write(&mut self, address: u16, value: u8) {
+        self.check(BreakpointKind::Write, address, value);
+    }
+
+    fn check(&mut self, kind: BreakpointKind, address: u16, value: u8) -> bool {
+        let hit = self
+            .breakpoints
+            .iter()
+            .any(|bp| bp.matches(kind, address, &self.registers));
+
+        if hit {
+            self.hit = Some(Break {
+                kind,
+                address,
+                value,
+            });
+        }
+
+        hit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Breakpoint, BreakpointKind, Comparison, Condition, Debugger, Register, Registers};
+
+    #[test]
+    fn test_conditional_breakpoint() {
+        let mut debugger = Debugger::default();
+        let condition = Condition::new(Register::X, Comparison::Greater, 2);
+        let registers = Registers {
+            x: 2,
+            ..Default::default()
+        };
+
+        debugger.add_breakpoint(Breakpoint::write(0x10).with_condition(condition));
+        debugger.on_instruction(registers, 0xEA);
+        debugger.on_write(0x10, 0);
+
+        assert_eq!(debugger.take_break(), None);
+
+        debugger.on_instruction(Registers { x: 3, ..registers }, 0xEA);
+        debugger.on_write(0x10, 0);
+
+        assert_eq!(
+            debugger.take_break().map(|hit| hit.kind),
+            Some(BreakpointKind::Write)
+        );
+    }
+
+    #[test]
+    fn test_execute_breakpoint_resume() {
+        let mut debugger = Debugger::default();
+        let registers = Registers {
+            pc: 0x8000,
+            ..Default::default()
+        };
+
+        debugger.add_breakpoint(Breakpoint::execute(0x8000));
+
+        assert!(debugger.on_instruction(registers, 0xEA));
+        assert!(!debugger.on_instruction(registers, 0xEA));
+        assert!(debugger.on_instruction(registers, 0xEA));
+        assert_eq!(debugger.instruction_count(), 1);
+    }
+}
@@ -0,0 +1,87 @@
+// https://www.qmtpro.com/~nes/misc/nestest.log
+
+use super::{Instruction, Registers};
+
+use std::{fmt, io};
+
+/// Receives one nestest formatted line per executed instruction.
+pub struct TraceLogger(Box<dyn FnMut(&str)>);
+
+impl TraceLogger {
+    /// Writes lines to `writer`, write errors are ignored.
+    pub fn new<W: io::Write + 'static>(mut writer: W) -> Self {
+        Self(Box::new(move |line| {
+            let _ = writeln!(writer, "{line}");
+        }))
+    }
+
+    pub fn with_callback<F: FnMut(&str) + 'static>(callback: F) -> Self {
+        Self(Box::new(callback))
+    }
+
+    pub(crate) fn log(&mut self, line: &str) {
+        (self.0)(line)
+    }
+}
+
+impl fmt::Debug for TraceLogger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TraceLogger").finish_non_exhaustive()
+    }
+}
+
+/// Formats a line like `C000  4C F5 C5  JMP $C5F5  A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7`.
+pub fn format_trace(
+    instruction: &Instruction,
+    registers: &Registers,
+    scanline: u16,
+    dot: u16,
+) -> String {
+    let bytes = instruction
+        .bytes
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let disassembly = match instruction.unofficial {
+        true => instruction.to_string(),
+        false => format!(" {instruction}"),
+    };
+
+    format!(
+        "{:04X}  {bytes:<8} {disassembly:<33}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{scanline:>3},{dot:>3} CYC:{}",
+        registers.pc, registers.a, registers.x, registers.y, registers.p, registers.sp, registers.cycle
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_trace;
+    use crate::{
+        debugger::{Instruction, Registers},
+        utils::test::LogLine,
+    };
+
+    #[test]
+    fn test_trace_format() {
+        let memory = [0x4C, 0xF5, 0xC5];
+        let instruction = Instruction::decode(0xC000, |adr| memory[adr as usize - 0xC000]);
+        let registers = Registers {
+            pc: 0xC000,
+            p: 0x24,
+            sp: 0xFD,
+            cycle: 7,
+            ..Default::default()
+        };
+
+        let line = format_trace(&instruction, &registers, 0, 21);
+        let parsed = LogLine::from_line(&line).unwrap();
+
+        assert_eq!(
+            line,
+            "C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7"
+        );
+        assert_eq!((parsed.pc, parsed.sp, parsed.cycle), (0xC000, 0xFD, 7));
+    }
+}
@@ -1,9 +1,13 @@
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Error {
     UnsupportedFileFormat,
-    UnsupportedVersion,
     UnexpectedEndOfInput { expected: String, length: usize },
-    UnsupportedMapper(u8),
+    EmptyPrgRom,
+    InvalidHeader(String),
+    UnsupportedMapper(u16),
+    InvalidSaveState,
+    UnsupportedSaveStateVersion(u16),
+    SaveStateRomMismatch,
 }
 
 impl Error {
@@ -19,14 +23,20 @@ impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Error::UnsupportedFileFormat => write!(f, "The loaded file is not an iNES file"),
-            Error::UnsupportedVersion => write!(f, "iNES 2.0 is not supported"),
             Error::UnexpectedEndOfInput { expected, length } => {
                 write!(
                     f,
                     "Unexpected end of input, expected {expected} (length: {length})",
                 )
             }
+            Error::EmptyPrgRom => write!(f, "The cartridge has no PRG ROM"),
+            Error::InvalidHeader(reason) => write!(f, "Invalid iNES header, {reason}"),
             Error::UnsupportedMapper(id) => write!(f, "Unsupported mapper {id}"),
+            Error::InvalidSaveState => write!(f, "The save state is invalid or corrupted"),
+            Error::UnsupportedSaveStateVersion(version) => {
+                write!(f, "Unsupported save state version {version}")
+            }
+            Error::SaveStateRomMismatch => write!(f, "The save state was made with another ROM"),
         }
     }
 }
@@ -3,6 +3,7 @@ pub mod bus;
 pub mod cartridge;
 pub mod controller;
 pub mod cpu;
+pub mod debugger;
 pub mod error;
 pub mod mappers;
 pub mod ppu;
@@ -17,32 +18,39 @@ use std::cell::Ref;
 
 use bus::MainBus;
 use cpu::Cpu;
+use debugger::{Break, Debugger, Instruction, Registers};
 use error::Error;
 use mappers::MapperChip;
-use utils::Reset;
+use utils::{Reset, Snapshot, SnapshotReader, SnapshotWriter};
+
+const SAVE_STATE_MAGIC: &[u8; 4] = b"MESS";
+const SAVE_STATE_VERSION: u16 = 3;
+const JSR_OPCODE: u8 = 0x20;
 
 #[derive(Debug)]
 pub struct Nes {
     pub(crate) cpu: Cpu,
+    rom_crc32: u32,
 }
 
 impl Nes {
     pub fn new(bytes: &[u8]) -> Result<Self, Error> {
         let mapper = MapperChip::try_from_bytes(bytes)?;
-        let bus = MainBus::new(mapper);
-        let cpu = Cpu::new(bus);
 
-        Ok(Self { cpu })
+        Ok(Self::with_mapper(mapper))
     }
 
     pub fn with_mapper(mapper: MapperChip) -> Self {
+        // taken before the game can rewrite flash PRG ROM
+        let rom_crc32 = mapper.get_cartridge().crc32();
         let bus = MainBus::new(mapper);
         let cpu = Cpu::new(bus);
 
-        Self { cpu }
+        Self { cpu, rom_crc32 }
     }
 
     pub fn set_mapper(&mut self, mapper: MapperChip) {
+        self.rom_crc32 = mapper.get_cartridge().crc32();
         self.cpu.bus.set_mapper(mapper);
     }
 
@@ -73,6 +81,84 @@ impl Nes {
         }
     }
 
+    pub fn registers(&self) -> Registers {
+        self.cpu.registers()
+    }
+
+    pub fn debugger(&self) -> &Debugger {
+        &self.cpu.bus.debugger
+    }
+
+    pub fn debugger_mut(&mut self) -> &mut Debugger {
+        &mut self.cpu.bus.debugger
+    }
+
+    /// Disassembles the instruction at `address` using the current CPU registers.
+    pub fn disassemble(&self, address: u16) -> Instruction {
+        self.cpu.disassemble(address)
+    }
+
+    /// Runs until the next instruction has been executed or a breakpoint is hit.
+    pub fn step_instruction(&mut self) -> Option<Break> {
+        let count = self.cpu.bus.debugger.instruction_count();
+
+        self.cpu.bus.debugger.take_break();
+
+        while self.cpu.bus.debugger.instruction_count() == count {
+            self.step();
+
+            if let Some(hit) = self.cpu.bus.debugger.take_break() {
+                return Some(hit);
+            }
+        }
+
+        None
+    }
+
+    /// Same as [`Nes::step_instruction`] but runs subroutine calls until they return
+    /// or the next frame is complete.
+    pub fn step_over(&mut self) -> Option<Break> {
+        let Registers { pc, sp, .. } = self.registers();
+
+        if self.cpu.bus.peek_u8(pc) != JSR_OPCODE {
+            return self.step_instruction();
+        }
+
+        let mut left_vblank = !self.cpu.bus.ppu.is_vblank();
+
+        loop {
+            let hit = self.step_instruction();
+            let registers = self.registers();
+
+            if hit.is_some() || registers.pc == pc.wrapping_add(3) && registers.sp == sp {
+                return hit;
+            }
+
+            match self.cpu.bus.ppu.is_vblank() {
+                true if left_vblank => return None,
+                true => {}
+                false => left_vblank = true,
+            }
+        }
+    }
+
+    /// Runs until a breakpoint is hit or the next frame is complete.
+    pub fn run_until_break(&mut self) -> Option<Break> {
+        self.cpu.bus.debugger.take_break();
+
+        for vblank in [true, false] {
+            while self.cpu.bus.ppu.is_vblank() == vblank {
+                self.step();
+
+                if let Some(hit) = self.cpu.bus.debugger.take_break() {
+                    return Some(hit);
+                }
+            }
+        }
+
+        None
+    }
+
     pub fn get_audio_buffer(&self) -> Ref<[f32]> {
         Ref::map(self.cpu.apu.borrow(), |apu| apu.get_buffer())
     }
@@ -88,4 +174,389 @@ impl Nes {
     pub fn set_controller_state(&mut self, id: usize, state: u8) {
         self.cpu.bus.controller.set_state(id, state);
     }
+
+    /// Returns the battery-backed RAM, or `None` if the cartridge has no battery.
+    pub fn battery_ram(&self) -> Option<Ref<[u8]>> {
+        self.cpu.bus.mapper.battery_ram()
+    }
+
+    /// Restores RAM returned by [`Nes::battery_ram`], ignored if the cartridge has no battery.
+    pub fn load_battery_ram(&mut self, bytes: &[u8]) {
+        self.cpu.bus.mapper.load_battery_ram(bytes);
+    }
+
+    /// Returns whether the battery-backed RAM changed since the last call.
+    pub fn take_battery_ram_dirty(&mut self) -> bool {
+        self.cpu.bus.mapper.take_battery_ram_dirty()
+    }
+
+    pub fn peek_cpu(&self, address: u16) -> u8 {
+        self.cpu.bus.peek_u8(address)
+    }
+
+    pub fn poke_cpu(&mut self, address: u16, value: u8) {
+        self.cpu.bus.poke_u8(address, value);
+    }
+
+    pub fn peek_ppu(&self, address: u16) -> u8 {
+        self.cpu.bus.ppu.bus.peek_u8(address)
+    }
+
+    pub fn poke_ppu(&mut self, address: u16, value: u8) {
+        self.cpu.bus.ppu.bus.poke_u8(address, value);
+    }
+
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut state = SnapshotWriter::default();
+        state.write_bytes(SAVE_STATE_MAGIC);
+        state.write_u16(SAVE_STATE_VERSION);
+        self.save_fingerprint(&mut state);
+        self.cpu.save(&mut state);
+        state.into_bytes()
+    }
+
+    /// Restores a state created by [`Nes::save_state`], the current state is kept on error.
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let backup = self.save_state();
+
+        self.try_load_state(bytes).or_else(|error| {
+            self.try_load_state(&backup)?;
+            Err(error)
+        })
+    }
+
+    /// Identifies the loaded game so states from another ROM are rejected.
+    fn save_fingerprint(&self, state: &mut SnapshotWriter) {
+        let cartridge = self.cpu.bus.mapper.get_cartridge();
+        state.write_u16(cartridge.header.mapper);
+        state.write_u8(cartridge.header.submapper);
+        state.write_u32(self.rom_crc32);
+    }
+
+    fn try_load_state(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let mut state = SnapshotReader::new(bytes);
+        let mut magic = [0; 4];
+
+        state
+            .read_bytes(&mut magic)
+            .map_err(|_| Error::InvalidSaveState)?;
+
+        if &magic != SAVE_STATE_MAGIC {
+            return Err(Error::InvalidSaveState);
+        }
+
+        let version = state.read_u16()?;
+
+        if version != SAVE_STATE_VERSION {
+            return Err(Error::UnsupportedSaveStateVersion(version));
+        }
+
+        let mut fingerprint = SnapshotWriter::default();
+        self.save_fingerprint(&mut fingerprint);
+
+        for byte in fingerprint.into_bytes() {
+            if state.read_u8()? != byte {
+                return Err(Error::SaveStateRomMismatch);
+            }
+        }
+
+        self.cpu.load(&mut state)?;
+
+        match state.is_empty() {
+            true => Ok(()),
+            false => Err(Error::InvalidSaveState),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Nes;
+    use crate::{
+        apu::{AudioChip, ExpansionAudio},
+        bus::Bus,
+        cartridge::{Cartridge, Mirroring, PrgPage},
+        debugger::{Breakpoint, BreakpointKind, Comparison, Condition, Register, TraceLogger},
+        error::Error,
+        mappers::{Mapper, MapperChip},
+        utils::{
+            test::{nrom, NESTEST_ROM},
+            Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter,
+        },
+    };
+
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn test_save_state() {
+        let mut nes = Nes::new(NESTEST_ROM).unwrap();
+
+        nes.step_frame();
+        nes.step_vblank();
+
+        let state = nes.save_state();
+        let frame = nes.get_frame_buffer().to_vec();
+
+        nes.step_frame();
+        nes.step_vblank();
+        nes.load_state(&state).unwrap();
+
+        assert_eq!(nes.save_state(), state);
+        assert_eq!(nes.get_frame_buffer(), frame);
+    }
+
+    #[rustfmt::skip]
+    const PROGRAM: &[u8] = &[
+        0xA2, 0x00,       // 8000: LDX #$00
+        0xE8,             // 8002: INX
+        0x86, 0x10,       // 8003: STX $10
+        0x20, 0x0B, 0x80, // 8005: JSR $800B
+        0x4C, 0x02, 0x80, // 8008: JMP $8002
+        0xA5, 0x10,       // 800B: LDA $10
+        0x60,             // 800D: RTS
+    ];
+
+    #[test]
+    fn test_breakpoints() {
+        let mut nes = Nes::new(&nrom(PROGRAM)).unwrap();
+        let condition = Condition::new(Register::X, Comparison::Equal, 3);
+
+        nes.debugger_mut()
+            .add_breakpoint(Breakpoint::execute(0x800B));
+
+        let hit = nes.run_until_break().unwrap();
+
+        assert_eq!(
+            (hit.kind, hit.address, hit.value),
+            (BreakpointKind::Execute, 0x800B, 0xA5)
+        );
+        assert_eq!(nes.registers().pc, 0x800B);
+
+        nes.debugger_mut().clear_breakpoints();
+        nes.debugger_mut()
+            .add_breakpoint(Breakpoint::write(0x10).with_condition(condition));
+
+        let hit = nes.run_until_break().unwrap();
+
+        assert_eq!(
+            (hit.kind, hit.address, hit.value),
+            (BreakpointKind::Write, 0x10, 3)
+        );
+        assert_eq!(nes.registers().pc, 0x8005);
+
+        nes.debugger_mut().clear_breakpoints();
+        nes.debugger_mut().add_breakpoint(Breakpoint::read(0x10));
+
+        assert_eq!(
+            nes.step_over().map(|hit| hit.kind),
+            Some(BreakpointKind::Read)
+        );
+        assert_eq!(nes.registers().pc, 0x800D);
+    }
+
+    #[test]
+    fn test_trace_logger() {
+        let mut nes = Nes::new(&nrom(PROGRAM)).unwrap();
+        let lines = Rc::new(RefCell::new(Vec::new()));
+        let sink = lines.clone();
+        let logger =
+            TraceLogger::with_callback(move |line| sink.borrow_mut().push(line.to_string()));
+
+        nes.debugger_mut().set_trace_logger(logger);
+
+        for _ in 0..3 {
+            nes.step_instruction();
+        }
+
+        let lines = lines.borrow();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("8000  A2 00     LDX #$00"));
+        assert!(lines[2].starts_with("8003  86 10     STX $10 = 00"));
+        assert!(lines[2].contains("A:00 X:01 Y:00"));
+    }
+
+    #[test]
+    fn test_step_over() {
+        let mut nes = Nes::new(&nrom(PROGRAM)).unwrap();
+
+        for _ in 0..3 {
+            assert_eq!(nes.step_instruction(), None);
+        }
+
+        assert_eq!(nes.registers().pc, 0x8005);
+        assert_eq!(nes.disassemble(0x8003).to_string(), "STX $10 = 01");
+        assert_eq!(nes.disassemble(0x8005).to_string(), "JSR $800B");
+        assert_eq!(nes.step_over(), None);
+        assert_eq!(nes.registers().pc, 0x8008);
+        assert_eq!(nes.registers().a, 1);
+    }
+
+    #[test]
+    fn test_step_over_endless_subroutine() {
+        #[rustfmt::skip]
+        let program = [
+            0xEA,             // 8000: NOP
+            0x20, 0x04, 0x80, // 8001: JSR $8004
+            0x4C, 0x04, 0x80, // 8004: JMP $8004
+        ];
+        let mut nes = Nes::new(&nrom(&program)).unwrap();
+
+        assert_eq!(nes.step_instruction(), None);
+        assert_eq!(nes.step_over(), None);
+        assert_eq!(nes.registers().pc, 0x8004);
+        assert!(nes.cpu.bus.ppu.is_vblank());
+    }
+
+    #[test]
+    fn test_peek_poke() {
+        let mut nes = Nes::new(NESTEST_ROM).unwrap();
+
+        nes.step_frame();
+        nes.poke_cpu(0x0010, 0x42);
+        nes.poke_ppu(0x2000, 0x24);
+        nes.poke_ppu(0x3F01, 0x30);
+
+        let state = nes.save_state();
+
+        for address in 0..=0xFFFF {
+            nes.peek_cpu(address);
+            nes.peek_ppu(address);
+        }
+
+        assert_eq!(nes.save_state(), state);
+        assert_eq!(nes.peek_cpu(0x0810), 0x42);
+        assert_eq!(nes.peek_ppu(0x2000), 0x24);
+        assert_eq!(nes.peek_ppu(0x3F01), 0x30);
+        assert_eq!(nes.peek_cpu(0x2002) & 0x80, 0x80);
+    }
+
+    #[test]
+    fn test_battery_ram() {
+        let mut rom = NESTEST_ROM.to_vec();
+        let mut nes = Nes::new(&rom).unwrap();
+
+        assert!(nes.battery_ram().is_none());
+
+        rom[6] |= 0x02; // battery
+        nes.set_cartridge(&rom).unwrap();
+        nes.load_battery_ram(&[0xAB; 16]);
+
+        assert!(!nes.take_battery_ram_dirty());
+        assert_eq!(nes.battery_ram().unwrap()[..16], [0xAB; 16]);
+
+        nes.cpu.bus.write_u8(0x6010, 0xCD);
+
+        assert!(nes.take_battery_ram_dirty());
+        assert!(!nes.take_battery_ram_dirty());
+        assert_eq!(nes.battery_ram().unwrap()[0x10], 0xCD);
+    }
+
+    #[test]
+    fn test_invalid_save_state() {
+        let mut nes = Nes::new(NESTEST_ROM).unwrap();
+        let mut state = nes.save_state();
+
+        nes.step_frame();
+
+        let current = nes.save_state();
+
+        assert_eq!(nes.load_state(&[]), Err(Error::InvalidSaveState));
+        assert!(nes.load_state(&state[..state.len() / 2]).is_err());
+        assert_eq!(nes.save_state(), current);
+
+        state[8] = 0xFF; // version
+
+        assert_eq!(
+            nes.load_state(&state),
+            Err(Error::UnsupportedSaveStateVersion(0xFF))
+        );
+
+        let other = Nes::new(&nrom(&[0x4C, 0x00, 0x80])).unwrap().save_state();
+
+        assert_eq!(nes.load_state(&other), Err(Error::SaveStateRomMismatch));
+        assert_eq!(nes.save_state(), current);
+    }
+
+    /// NROM board with a square wave expansion chip.
+    #[derive(Debug)]
+    struct SquareMapper {
+        cartridge: Cartridge,
+        cycle: u32,
+    }
+
+    impl ExpansionAudio for SquareMapper {
+        fn chip(&self) -> AudioChip {
+            AudioChip::Vrc6
+        }
+
+        fn get_sample(&self) -> f32 {
+            (self.cycle / 100 % 2) as f32
+        }
+    }
+
+    impl Mapper for SquareMapper {
+        fn read(&self, address: u16) -> Option<u8> {
+            match address {
+                0x8000..=0xFFFF => Some(self.cartridge.read_prg_rom(address, PrgPage::Index32(0))),
+                _ => None,
+            }
+        }
+
+        fn write(&mut self, _address: u16, _value: u8) {}
+
+        fn get_mirroring(&self) -> Mirroring {
+            Mirroring::Vertical
+        }
+
+        fn get_cartridge(&self) -> &Cartridge {
+            &self.cartridge
+        }
+
+        fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+            &mut self.cartridge
+        }
+
+        fn expansion_audio(&self) -> Option<&dyn ExpansionAudio> {
+            Some(self)
+        }
+    }
+
+    impl Clock for SquareMapper {
+        fn tick(&mut self) {
+            self.cycle += 1;
+        }
+    }
+
+    impl Reset for SquareMapper {
+        fn reset(&mut self) {
+            self.cycle = 0;
+        }
+    }
+
+    impl Snapshot for SquareMapper {
+        fn save(&self, _state: &mut SnapshotWriter) {}
+
+        fn load(&mut self, _state: &mut SnapshotReader) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_expansion_audio() {
+        let rom = nrom(&[0x4C, 0x00, 0x80]); // JMP $8000
+        let mut silent = Nes::new(&rom).unwrap();
+        let mut nes = Nes::with_mapper(MapperChip::new(SquareMapper {
+            cartridge: Cartridge::try_from_bytes(&rom).unwrap(),
+            cycle: 0,
+        }));
+
+        silent.step_frame();
+        nes.step_frame();
+
+        let peak = |buffer: &[f32]| buffer.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+
+        assert_eq!(peak(&silent.get_audio_buffer()), 0.0);
+        assert!(peak(&nes.get_audio_buffer()) > 0.1);
+    }
 }
@@ -3,7 +3,8 @@
 use super::Mapper;
 use crate::{
     cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
-    utils::Reset,
+    error::Error,
+    utils::{Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
 };
 
 #[derive(Debug)]
@@ -18,15 +19,14 @@ impl NRom {
 }
 
 impl Mapper for NRom {
-    fn read(&self, address: u16) -> u8 {
-        match address {
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
             0x0000..=0x1FFF => self.cartridge.read_chr(address, ChrPage::Index8(0)),
-            0x4020..=0x5FFF => 0,
             0x6000..=0x7FFF => self.cartridge.read_prg_ram(address),
             0x8000..=0xBFFF => self.cartridge.read_prg_rom(address, PrgPage::Index16(0)),
             0xC000..=0xFFFF => self.cartridge.read_prg_rom(address, PrgPage::Last16),
-            _ => panic!("Trying to read from an invalid address: 0x{address:x}"),
-        }
+            _ => return None,
+        })
     }
 
     fn write(&mut self, address: u16, value: u8) {
@@ -38,8 +38,28 @@ impl Mapper for NRom {
     fn get_mirroring(&self) -> Mirroring {
         self.cartridge.header.mirroring
     }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
 }
 
+impl Clock for NRom {}
+
 impl Reset for NRom {
     fn reset(&mut self) {}
 }
+
+impl Snapshot for NRom {
+    fn save(&self, state: &mut SnapshotWriter) {
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.cartridge.load(state)
+    }
+}
@@ -3,7 +3,8 @@
 use super::Mapper;
 use crate::{
     cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
-    utils::{BitFlag, Reset},
+    error::Error,
+    utils::{BitFlag, Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
 };
 
 #[derive(Debug)]
@@ -14,6 +15,8 @@ pub struct SxRom {
     chr_bank_low: u8,
     chr_bank_high: u8,
     prg_bank: u8,
+    write_blocked: bool,
+    chr_a12: bool,
 }
 
 impl SxRom {
@@ -25,9 +28,75 @@ impl SxRom {
             chr_bank_low: 0,
             chr_bank_high: 0,
             prg_bank: 0,
+            write_blocked: false,
+            chr_a12: false,
         }
     }
 
+    fn prg_page(&self, address: u16) -> PrgPage {
+        let bank = self.prg_bank & 0b1111;
+        let page = match (self.control.get_range(2..4), address) {
+            (0 | 1, 0x8000..=0xBFFF) => bank & 0b1110,
+            (0 | 1, _) => bank | 1,
+            (2, 0x8000..=0xBFFF) => 0,
+            (2, _) => bank,
+            (_, 0x8000..=0xBFFF) => bank,
+            _ => 0b1111,
+        };
+        // SUROM and SXROM select the 256 KB outer bank with bit 4 of the CHR bank on the CHR lines,
+        // which follows the last PPU A12 in 4 KB mode
+        let chr_bank = match self.control.contains(4) && self.chr_a12 {
+            true => self.chr_bank_high,
+            false => self.chr_bank_low,
+        };
+        let outer = match self.cartridge.header.prg_rom_pages > 16 {
+            true => chr_bank & 0b10000,
+            false => 0,
+        };
+        let count = self.cartridge.header.prg_rom_pages.max(1) as usize;
+
+        PrgPage::Index16((outer | page) as usize % count)
+    }
+
+    fn chr_page(&self, address: u16) -> ChrPage {
+        let count = match self.cartridge.header.chr_rom_pages {
+            0 => self.cartridge.chr_ram.len() / 0x1000,
+            pages => pages as usize * 2,
+        }
+        .max(1);
+
+        match (self.control.contains(4), address) {
+            (false, _) => ChrPage::Index8((self.chr_bank_low as usize % count) >> 1),
+            (true, 0x0000..=0x0FFF) => ChrPage::Index4(self.chr_bank_low as usize % count),
+            (true, _) => ChrPage::Index4(self.chr_bank_high as usize % count),
+        }
+    }
+
+    fn prg_ram_page(&self) -> u8 {
+        // SOROM and SXROM have 16 and 32 KB of PRG RAM
+        match self.cartridge.prg_ram.len() {
+            0x8000 => self.chr_bank_low.get_range(2..4),
+            0x4000 => self.chr_bank_low.get(3),
+            _ => 0,
+        }
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        // writes on consecutive cycles are ignored, only the first write of a RMW instruction lands
+        if std::mem::replace(&mut self.write_blocked, true) || !self.shift(value) {
+            return;
+        }
+
+        match address {
+            0x8000..=0x9FFF => self.control = self.shift,
+            0xA000..=0xBFFF => self.chr_bank_low = self.shift,
+            0xC000..=0xDFFF => self.chr_bank_high = self.shift,
+            _ => self.prg_bank = self.shift & 0b1111,
+        }
+
+        self.shift = 0b10000;
+    }
+
     fn shift(&mut self, value: u8) -> bool {
         if value.contains(7) {
             self.shift = 0b10000;
@@ -41,74 +110,58 @@ impl SxRom {
 }
 
 impl Mapper for SxRom {
-    fn read(&self, address: u16) -> u8 {
-        match address {
-            0x0000..=0x1FFF if !self.control.contains(4) => self
-                .cartridge
-                .read_chr(address, ChrPage::Index8(self.chr_bank_low & 0b11110)),
-            0x0000..=0x0FFF => self
-                .cartridge
-                .read_chr(address, ChrPage::Index4(self.chr_bank_low)),
-            0x1000..=0x1FFF => self
-                .cartridge
-                .read_chr(address, ChrPage::Index4(self.chr_bank_high)),
-            0x4020..=0x5FFF => 0,
-            0x6000..=0x7FFF => self.cartridge.read_prg_ram(address),
-            0x8000..=0xFFFF if !self.control.contains(3) => self
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
+            0x0000..=0x1FFF => self.cartridge.read_chr(address, self.chr_page(address)),
+            0x6000..=0x7FFF => self
                 .cartridge
-                .read_prg_rom(address, PrgPage::Index32(self.prg_bank & 0b11110)),
-            0x8000..=0xBFFF if self.control.contains(2) => self
-                .cartridge
-                .read_prg_rom(address, PrgPage::Index16(self.prg_bank)),
-            0xC000..=0xFFFF if self.control.contains(2) => {
-                self.cartridge.read_prg_rom(address, PrgPage::Last16)
-            }
-            0x8000..=0xBFFF if !self.control.contains(2) => {
-                self.cartridge.read_prg_rom(address, PrgPage::Index16(0))
-            }
-            0xC000..=0xFFFF if !self.control.contains(2) => self
-                .cartridge
-                .read_prg_rom(address, PrgPage::Index16(self.prg_bank)),
-            _ => panic!("Trying to read from invalid address: 0x{address:x}"),
-        }
+                .read_prg_ram_page(address, self.prg_ram_page()),
+            0x8000..=0xFFFF => self.cartridge.read_prg_rom(address, self.prg_page(address)),
+            _ => return None,
+        })
     }
 
     fn write(&mut self, address: u16, value: u8) {
         match address {
-            0x0000..=0x1FFF if !self.control.contains(4) => {
-                let index = self.chr_bank_low & 0b11110;
-                self.cartridge
-                    .write_chr_ram(address, value, ChrPage::Index8(index))
-            }
-            0x0000..=0x0FFF => {
-                self.cartridge
-                    .write_chr_ram(address, value, ChrPage::Index4(self.chr_bank_low))
+            0x0000..=0x1FFF => {
+                let page = self.chr_page(address);
+                self.cartridge.write_chr(address, value, page)
             }
-            0x1000..=0x1FFF => {
-                self.cartridge
-                    .write_chr_ram(address, value, ChrPage::Index4(self.chr_bank_high))
-            }
-            0x6000..=0x7FFF => self.cartridge.write_prg_ram(address, value),
-            0x8000..=0xFFFF if self.shift(value) => {
-                match address {
-                    0x8000..=0x9FFF => self.control = self.shift,
-                    0xA000..=0xBFFF => self.chr_bank_low = self.shift,
-                    0xC000..=0xDFFF => self.chr_bank_high = self.shift,
-                    _ => self.prg_bank = self.shift & 0b1111,
-                }
-                self.shift = 0b10000;
+            0x6000..=0x7FFF => {
+                let page = self.prg_ram_page();
+                self.cartridge.write_prg_ram_page(address, value, page)
             }
+            0x8000..=0xFFFF => self.write_register(address, value),
             _ => {}
         }
     }
 
-    fn get_mirroring(&self) -> crate::cartridge::Mirroring {
+    fn get_mirroring(&self) -> Mirroring {
         match self.control & 0b11 {
+            0 => Mirroring::OneScreenLower,
+            1 => Mirroring::OneScreenUpper,
             2 => Mirroring::Vertical,
-            3 => Mirroring::Horizontal,
-            _ => Mirroring::OneScreen,
+            _ => Mirroring::Horizontal,
         }
     }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    fn on_ppu_address(&mut self, address: u16) {
+        self.chr_a12 = address.contains(12);
+    }
+}
+
+impl Clock for SxRom {
+    fn tick(&mut self) {
+        self.write_blocked = false;
+    }
 }
 
 impl Reset for SxRom {
@@ -118,5 +171,177 @@ impl Reset for SxRom {
         self.chr_bank_low = 0;
         self.chr_bank_high = 0;
         self.prg_bank = 0;
+        self.write_blocked = false;
+        self.chr_a12 = false;
+    }
+}
+
+impl Snapshot for SxRom {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.shift);
+        state.write_u8(self.control);
+        state.write_u8(self.chr_bank_low);
+        state.write_u8(self.chr_bank_high);
+        state.write_u8(self.prg_bank);
+        state.write_bool(self.write_blocked);
+        state.write_bool(self.chr_a12);
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.shift = state.read_u8()?;
+        self.control = state.read_u8()?;
+        self.chr_bank_low = state.read_u8()?;
+        self.chr_bank_high = state.read_u8()?;
+        self.prg_bank = state.read_u8()?;
+        self.write_blocked = state.read_bool()?;
+        self.chr_a12 = state.read_bool()?;
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SxRom;
+    use crate::{
+        cartridge::{Cartridge, Header},
+        mappers::Mapper,
+        utils::{Clock, Snapshot, SnapshotReader, SnapshotWriter},
+    };
+
+    fn sxrom(prg_rom_pages: u16, prg_ram_size: usize) -> SxRom {
+        SxRom::new(Cartridge {
+            header: Header {
+                prg_rom_pages,
+                chr_rom_pages: 0,
+                prg_ram_size,
+                mapper: 1,
+                ..Cartridge::default().header
+            },
+            prg_rom: (0..prg_rom_pages as u8)
+                .flat_map(|bank| vec![bank; 0x4000])
+                .collect(),
+            chr_ram: vec![0; 0x2000],
+            prg_ram: vec![0; prg_ram_size],
+            ..Default::default()
+        })
+    }
+
+    fn write_register(mapper: &mut SxRom, address: u16, value: u8) {
+        for bit in 0..5 {
+            mapper.write(address, value >> bit);
+            mapper.tick();
+        }
+    }
+
+    #[test]
+    fn test_sxrom_prg_32k_mode() {
+        let mut mapper = sxrom(8, 0x2000);
+
+        write_register(&mut mapper, 0x8000, 0b00000);
+        write_register(&mut mapper, 0xE000, 0b00101);
+
+        assert_eq!(mapper.read(0x8000), Some(4));
+        assert_eq!(mapper.read(0xC000), Some(5));
+    }
+
+    #[test]
+    fn test_surom_outer_bank() {
+        let mut mapper = sxrom(32, 0x2000);
+
+        write_register(&mut mapper, 0xE000, 0b00011);
+
+        assert_eq!(mapper.read(0x8000), Some(3));
+        assert_eq!(mapper.read(0xC000), Some(15));
+
+        write_register(&mut mapper, 0xA000, 0b10000);
+
+        assert_eq!(mapper.read(0x8000), Some(19));
+        assert_eq!(mapper.read(0xC000), Some(31));
+        assert_eq!(mapper.read(0x0000), Some(0));
+    }
+
+    #[test]
+    fn test_surom_outer_bank_4k_chr() {
+        let mut mapper = sxrom(32, 0x2000);
+
+        write_register(&mut mapper, 0x8000, 0b11100);
+        write_register(&mut mapper, 0xA000, 0b00000);
+        write_register(&mut mapper, 0xC000, 0b10000);
+
+        mapper.on_ppu_address(0x0000);
+        assert_eq!(mapper.read(0x8000), Some(0));
+
+        mapper.on_ppu_address(0x1000);
+        assert_eq!(mapper.read(0x8000), Some(16));
+        assert_eq!(mapper.read(0xC000), Some(31));
+
+        // in 8 KB mode the high register is ignored
+        write_register(&mut mapper, 0x8000, 0b01100);
+        assert_eq!(mapper.read(0x8000), Some(0));
+    }
+
+    #[test]
+    fn test_sxrom_snapshot() {
+        let mut mapper = sxrom(32, 0x2000);
+
+        write_register(&mut mapper, 0x8000, 0b11100);
+        write_register(&mut mapper, 0xC000, 0b10000);
+        mapper.on_ppu_address(0x1000);
+        mapper.write(0xE000, 0x80);
+
+        let mut state = SnapshotWriter::default();
+        mapper.save(&mut state);
+        let bytes = state.into_bytes();
+
+        let mut loaded = sxrom(32, 0x2000);
+        loaded.load(&mut SnapshotReader::new(&bytes)).unwrap();
+
+        assert_eq!(loaded.read(0x8000), Some(16));
+
+        // the write on the next cycle is still blocked after loading
+        loaded.write(0xE000, 0x01);
+        loaded.tick();
+        write_register(&mut loaded, 0xE000, 0x02);
+
+        assert_eq!(loaded.read(0x8000), Some(18));
+    }
+
+    #[test]
+    fn test_sxrom_prg_ram_banks() {
+        let mut mapper = sxrom(32, 0x8000);
+
+        for bank in 0..4 {
+            write_register(&mut mapper, 0xA000, bank << 2);
+            mapper.write(0x6000, bank);
+        }
+
+        for bank in 0..4 {
+            write_register(&mut mapper, 0xA000, bank << 2);
+            assert_eq!(mapper.read(0x6000), Some(bank));
+        }
+
+        assert_eq!(mapper.get_cartridge().prg_ram[0x6000], 3);
+    }
+
+    #[test]
+    fn test_sxrom_consecutive_writes() {
+        let mut mapper = sxrom(8, 0x2000);
+
+        // the second write of a read-modify-write instruction is ignored
+        for bit in 0..5 {
+            mapper.write(0xE000, 0x03 >> bit);
+            mapper.write(0xE000, 0x80);
+            mapper.tick();
+        }
+
+        assert_eq!(mapper.read(0x8000), Some(3));
+
+        mapper.write(0xE000, 0x80);
+        mapper.write(0xE000, 0x01);
+        mapper.tick();
+        write_register(&mut mapper, 0xE000, 0x02);
+
+        assert_eq!(mapper.read(0x8000), Some(2));
     }
 }
@@ -1,47 +1,56 @@
 // https://www.nesdev.org/wiki/UxROM
 
-use super::Mapper;
+use super::{bus_conflict, has_bus_conflicts, Mapper};
 use crate::{
     cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
-    utils::Reset,
+    error::Error,
+    utils::{Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
 };
 
 #[derive(Debug)]
 pub struct UxRom {
     cartridge: Cartridge,
     prg_bank: u8,
+    bus_conflicts: bool,
 }
 
 impl UxRom {
     pub fn new(cartridge: Cartridge) -> Self {
+        let bus_conflicts = has_bus_conflicts(&cartridge.header);
+
         Self {
             cartridge,
             prg_bank: 0,
+            bus_conflicts,
         }
     }
 }
 
 impl Mapper for UxRom {
-    fn read(&self, address: u16) -> u8 {
-        match address {
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
             0x0000..=0x1FFF => self.cartridge.read_chr(address, ChrPage::Index8(0)),
-            0x4020..=0x5FFF => 0,
             0x6000..=0x7FFF => self.cartridge.read_prg_ram(address),
             0x8000..=0xBFFF => self
                 .cartridge
-                .read_prg_rom(address, PrgPage::Index16(self.prg_bank)),
+                .read_prg_rom(address, PrgPage::Index16(self.prg_bank as usize)),
             0xC000..=0xFFFF => self.cartridge.read_prg_rom(address, PrgPage::Last16),
-            _ => panic!("Trying to read from an invalid address: 0x{address:x}"),
-        }
+            _ => return None,
+        })
     }
 
     fn write(&mut self, address: u16, value: u8) {
         match address {
-            0x0000..=0x1FFF => self
-                .cartridge
-                .write_chr_ram(address, value, ChrPage::Index8(0)),
+            0x0000..=0x1FFF => self.cartridge.write_chr(address, value, ChrPage::Index8(0)),
             0x6000..=0x7FFF => self.cartridge.write_prg_ram(address, value),
-            0x8000..=0xFFFF => self.prg_bank = value & 0b1111,
+            0x8000..=0xFFFF => {
+                let value = match self.bus_conflicts {
+                    true => bus_conflict(self, address, value),
+                    false => value,
+                };
+
+                self.prg_bank = value & 0b1111;
+            }
             _ => {}
         }
     }
@@ -49,10 +58,69 @@ impl Mapper for UxRom {
     fn get_mirroring(&self) -> Mirroring {
         self.cartridge.header.mirroring
     }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
 }
 
+impl Clock for UxRom {}
+
 impl Reset for UxRom {
     fn reset(&mut self) {
         self.prg_bank = 0;
     }
 }
+
+impl Snapshot for UxRom {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.prg_bank);
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.prg_bank = state.read_u8()?;
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UxRom;
+    use crate::{
+        cartridge::{Cartridge, Header},
+        mappers::Mapper,
+    };
+
+    fn uxrom(submapper: u8) -> UxRom {
+        UxRom::new(Cartridge {
+            header: Header {
+                prg_rom_pages: 8,
+                chr_rom_pages: 0,
+                mapper: 2,
+                submapper,
+                ..Cartridge::default().header
+            },
+            prg_rom: (0..8).flat_map(|bank| vec![bank | 0xF0; 0x4000]).collect(),
+            chr_ram: vec![0; 0x2000],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_uxrom_bus_conflicts() {
+        for (submapper, expected) in [(0, 0xF6), (1, 0xF6), (2, 0xF4)] {
+            let mut mapper = uxrom(submapper);
+
+            mapper.write(0xC000, 0x04);
+            // conflicts with 0xF4 on submapper 2
+            mapper.write(0x8000, 0x06);
+
+            assert_eq!(mapper.read(0x8000), Some(expected));
+        }
+    }
+}
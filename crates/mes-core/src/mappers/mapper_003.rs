@@ -1,48 +1,59 @@
 // https://www.nesdev.org/wiki/INES_Mapper_003
 
-use super::Mapper;
+use super::{bus_conflict, has_bus_conflicts, Mapper};
 use crate::{
     cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
-    utils::Reset,
+    error::Error,
+    utils::{Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
 };
 
 #[derive(Debug)]
 pub struct CnRom {
     cartridge: Cartridge,
     chr_bank: u8,
+    bus_conflicts: bool,
 }
 
 impl CnRom {
     pub fn new(cartridge: Cartridge) -> Self {
+        let bus_conflicts = has_bus_conflicts(&cartridge.header);
+
         Self {
             cartridge,
             chr_bank: 0,
+            bus_conflicts,
         }
     }
 }
 
 impl Mapper for CnRom {
-    fn read(&self, address: u16) -> u8 {
-        match address {
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
             0x0000..=0x1FFF => self
                 .cartridge
-                .read_chr(address, ChrPage::Index8(self.chr_bank)),
-            0x4020..=0x5FFF => 0,
+                .read_chr(address, ChrPage::Index8(self.chr_bank as usize)),
             0x6000..=0x7FFF => self.cartridge.read_prg_ram(address),
             0x8000..=0xBFFF => self.cartridge.read_prg_rom(address, PrgPage::Index16(0)),
             0xC000..=0xFFFF => self.cartridge.read_prg_rom(address, PrgPage::Last16),
-            _ => panic!("Trying to read from an invalid address: 0x{address:x}"),
-        }
+            _ => return None,
+        })
     }
 
     fn write(&mut self, address: u16, value: u8) {
         match address {
             0x0000..=0x1FFF => {
                 self.cartridge
-                    .write_chr_ram(address, value, ChrPage::Index8(self.chr_bank))
+                    .write_chr(address, value, ChrPage::Index8(self.chr_bank as usize))
             }
             0x6000..=0x7FFF => self.cartridge.write_prg_ram(address, value),
-            0x8000..=0xFFFF => self.chr_bank = value,
+            0x8000..=0xFFFF => {
+                let value = match self.bus_conflicts {
+                    true => bus_conflict(self, address, value),
+                    false => value,
+                };
+
+                self.chr_bank = value;
+            }
             _ => {}
         }
     }
@@ -50,10 +61,81 @@ impl Mapper for CnRom {
     fn get_mirroring(&self) -> Mirroring {
         self.cartridge.header.mirroring
     }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
 }
 
+impl Clock for CnRom {}
+
 impl Reset for CnRom {
     fn reset(&mut self) {
         self.chr_bank = 0;
     }
 }
+
+impl Snapshot for CnRom {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.chr_bank);
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.chr_bank = state.read_u8()?;
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CnRom;
+    use crate::{
+        cartridge::{Cartridge, Header},
+        mappers::Mapper,
+        utils::test::{run_blargg_rom, test_rom},
+    };
+
+    fn cnrom(submapper: u8) -> CnRom {
+        CnRom::new(Cartridge {
+            header: Header {
+                prg_rom_pages: 2,
+                chr_rom_pages: 4,
+                mapper: 3,
+                submapper,
+                ..Cartridge::default().header
+            },
+            prg_rom: (0..0x8000).map(|address| address as u8).collect(),
+            chr_rom: (0..4).flat_map(|bank| vec![bank; 0x2000]).collect(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_cnrom_bus_conflicts() {
+        for (submapper, expected) in [(0, 3), (1, 3), (2, 1)] {
+            let mut mapper = cnrom(submapper);
+
+            // the ROM holds 0x01 at $8001
+            mapper.write(0x8001, 0x03);
+
+            assert_eq!(mapper.read(0x0000), Some(expected));
+        }
+    }
+
+    #[test]
+    fn test_cnrom_rom() {
+        let rom = test_rom("ppu_read_buffer/test_ppu_read_buffer.nes");
+        let cartridge = Cartridge::try_from_bytes(&rom).unwrap();
+
+        assert_eq!(cartridge.header.mapper, 3);
+
+        let (status, output) = run_blargg_rom(&rom);
+
+        assert_eq!(status, 0, "{output}");
+    }
+}
@@ -0,0 +1,358 @@
+// https://www.nesdev.org/wiki/MMC3
+
+use super::Mapper;
+use crate::{
+    cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
+    error::Error,
+    utils::{BitFlag, Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+// M2 cycles A12 has to stay low before a rising edge is seen by the counter
+const A12_FILTER: u8 = 3;
+
+#[derive(Debug)]
+pub struct TxRom {
+    cartridge: Cartridge,
+    bank_select: u8,
+    registers: [u8; 8],
+    mirroring: Mirroring,
+    prg_ram_protect: u8,
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    interrupt: bool,
+    a12: bool,
+    a12_low_cycles: u8,
+}
+
+impl TxRom {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let mirroring = cartridge.header.mirroring;
+
+        Self {
+            cartridge,
+            bank_select: 0,
+            registers: [0; 8],
+            mirroring,
+            prg_ram_protect: 0,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            interrupt: false,
+            a12: false,
+            a12_low_cycles: 0,
+        }
+    }
+
+    fn prg_bank(&self, address: u16) -> usize {
+        let second_last = self.prg_bank_count() - 2;
+        let swap = self.bank_select.contains(6);
+        let bank = match (address, swap) {
+            (0x8000..=0x9FFF, false) | (0xC000..=0xDFFF, true) => self.registers[6] as usize,
+            (0x8000..=0x9FFF, true) | (0xC000..=0xDFFF, false) => second_last,
+            (0xA000..=0xBFFF, _) => self.registers[7] as usize,
+            _ => second_last + 1,
+        };
+
+        bank % self.prg_bank_count()
+    }
+
+    fn chr_bank(&self, address: u16) -> usize {
+        let inverted = self.bank_select.contains(7);
+        let address = if inverted { address ^ 0x1000 } else { address };
+        let bank = match address {
+            0x0000..=0x07FF => (self.registers[0] & 0xFE) | (address >> 10) as u8 & 1,
+            0x0800..=0x0FFF => (self.registers[1] & 0xFE) | (address >> 10) as u8 & 1,
+            0x1000..=0x13FF => self.registers[2],
+            0x1400..=0x17FF => self.registers[3],
+            0x1800..=0x1BFF => self.registers[4],
+            _ => self.registers[5],
+        };
+
+        bank as usize % self.chr_bank_count()
+    }
+
+    fn prg_bank_count(&self) -> usize {
+        (self.cartridge.header.prg_rom_pages as usize * 2).max(2)
+    }
+
+    fn chr_bank_count(&self) -> usize {
+        match self.cartridge.header.chr_rom_pages {
+            0 => 8,
+            pages => pages as usize * 8,
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.prg_ram_protect.contains(7)
+    }
+
+    fn prg_ram_writable(&self) -> bool {
+        self.prg_ram_enabled() && !self.prg_ram_protect.contains(6)
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        match (address, address & 1) {
+            (0x8000..=0x9FFF, 0) => self.bank_select = value,
+            (0x8000..=0x9FFF, _) => self.registers[self.bank_select as usize & 0b111] = value,
+            (0xA000..=0xBFFF, 0) => {
+                if self.mirroring != Mirroring::FourScreen {
+                    self.mirroring = match value.contains(0) {
+                        true => Mirroring::Horizontal,
+                        false => Mirroring::Vertical,
+                    };
+                }
+            }
+            (0xA000..=0xBFFF, _) => self.prg_ram_protect = value,
+            (0xC000..=0xDFFF, 0) => self.irq_latch = value,
+            (0xC000..=0xDFFF, _) => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            }
+            (_, 0) => {
+                self.irq_enabled = false;
+                self.interrupt = false;
+            }
+            _ => self.irq_enabled = true,
+        }
+    }
+
+    // https://www.nesdev.org/wiki/MMC3#IRQ_Specifics
+    fn clock_irq_counter(&mut self) {
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.interrupt = true;
+        }
+    }
+}
+
+impl Mapper for TxRom {
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
+            0x0000..=0x1FFF => self
+                .cartridge
+                .read_chr(address, ChrPage::Index1(self.chr_bank(address))),
+            0x6000..=0x7FFF if self.prg_ram_enabled() => self.cartridge.read_prg_ram(address),
+            0x6000..=0x7FFF => return None,
+            0x8000..=0xFFFF => self
+                .cartridge
+                .read_prg_rom(address, PrgPage::Index8(self.prg_bank(address))),
+            _ => return None,
+        })
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                let bank = self.chr_bank(address);
+                self.cartridge
+                    .write_chr(address, value, ChrPage::Index1(bank))
+            }
+            0x6000..=0x7FFF if self.prg_ram_writable() => {
+                self.cartridge.write_prg_ram(address, value)
+            }
+            0x8000..=0xFFFF => self.write_register(address, value),
+            _ => {}
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    fn on_ppu_address(&mut self, address: u16) {
+        let a12 = address.contains(12);
+
+        if a12 && !self.a12 && self.a12_low_cycles >= A12_FILTER {
+            self.clock_irq_counter();
+        }
+
+        if a12 {
+            self.a12_low_cycles = 0;
+        }
+
+        self.a12 = a12;
+    }
+
+    fn irq(&self) -> bool {
+        self.interrupt
+    }
+}
+
+impl Clock for TxRom {
+    fn tick(&mut self) {
+        if !self.a12 {
+            self.a12_low_cycles = self.a12_low_cycles.saturating_add(1);
+        }
+    }
+}
+
+impl Reset for TxRom {
+    fn reset(&mut self) {
+        self.bank_select = 0;
+        self.registers = [0; 8];
+        self.mirroring = self.cartridge.header.mirroring;
+        self.prg_ram_protect = 0;
+        self.irq_latch = 0;
+        self.irq_counter = 0;
+        self.irq_reload = false;
+        self.irq_enabled = false;
+        self.interrupt = false;
+        self.a12 = false;
+        self.a12_low_cycles = 0;
+    }
+}
+
+impl Snapshot for TxRom {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.bank_select);
+        state.write_bytes(&self.registers);
+        self.mirroring.save(state);
+        state.write_u8(self.prg_ram_protect);
+        state.write_u8(self.irq_latch);
+        state.write_u8(self.irq_counter);
+        state.write_bool(self.irq_reload);
+        state.write_bool(self.irq_enabled);
+        state.write_bool(self.interrupt);
+        state.write_bool(self.a12);
+        state.write_u8(self.a12_low_cycles);
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.bank_select = state.read_u8()?;
+        state.read_bytes(&mut self.registers)?;
+        self.mirroring.load(state)?;
+        self.prg_ram_protect = state.read_u8()?;
+        self.irq_latch = state.read_u8()?;
+        self.irq_counter = state.read_u8()?;
+        self.irq_reload = state.read_bool()?;
+        self.irq_enabled = state.read_bool()?;
+        self.interrupt = state.read_bool()?;
+        self.a12 = state.read_bool()?;
+        self.a12_low_cycles = state.read_u8()?;
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TxRom;
+    use crate::{
+        mappers::Mapper,
+        utils::{test::banked_cartridge, Clock},
+    };
+
+    fn rise_a12(mapper: &mut TxRom) {
+        mapper.on_ppu_address(0x0000);
+
+        for _ in 0..3 {
+            mapper.tick();
+        }
+
+        mapper.on_ppu_address(0x1000);
+    }
+
+    #[test]
+    fn test_mmc3_large_prg() {
+        let mut mapper = TxRom::new(banked_cartridge(4, 128, 0));
+
+        mapper.write(0x8000, 6);
+        mapper.write(0x8001, 0xFD);
+
+        assert_eq!(mapper.read(0x8000), Some(0xFD));
+        assert_eq!(mapper.read(0xC000), Some(0xFE));
+        assert_eq!(mapper.read(0xE000), Some(0xFF));
+    }
+
+    #[test]
+    fn test_mmc3_prg_banking() {
+        let mut mapper = TxRom::new(banked_cartridge(4, 8, 8));
+
+        mapper.write(0x8000, 6);
+        mapper.write(0x8001, 3);
+        mapper.write(0x8000, 7);
+        mapper.write(0x8001, 5);
+
+        assert_eq!(mapper.read(0x8000), Some(3));
+        assert_eq!(mapper.read(0xA000), Some(5));
+        assert_eq!(mapper.read(0xC000), Some(14));
+        assert_eq!(mapper.read(0xE000), Some(15));
+
+        mapper.write(0x8000, 0b0100_0110);
+
+        assert_eq!(mapper.read(0x8000), Some(14));
+        assert_eq!(mapper.read(0xC000), Some(3));
+    }
+
+    #[test]
+    fn test_mmc3_chr_banking() {
+        let mut mapper = TxRom::new(banked_cartridge(4, 8, 8));
+
+        mapper.write(0x8000, 0);
+        mapper.write(0x8001, 9);
+        mapper.write(0x8000, 2);
+        mapper.write(0x8001, 42);
+
+        assert_eq!(mapper.read(0x0000), Some(8));
+        assert_eq!(mapper.read(0x0400), Some(9));
+        assert_eq!(mapper.read(0x1000), Some(42));
+
+        mapper.write(0x8000, 0b1000_0000);
+
+        assert_eq!(mapper.read(0x1000), Some(8));
+        assert_eq!(mapper.read(0x0000), Some(42));
+    }
+
+    #[test]
+    fn test_mmc3_irq_counter() {
+        let mut mapper = TxRom::new(banked_cartridge(4, 8, 8));
+
+        mapper.write(0xC000, 2);
+        mapper.write(0xC001, 0);
+        mapper.write(0xE001, 0);
+
+        rise_a12(&mut mapper); // reload
+        rise_a12(&mut mapper);
+
+        assert!(!mapper.irq());
+
+        rise_a12(&mut mapper);
+
+        assert!(mapper.irq());
+
+        mapper.write(0xE000, 0);
+
+        assert!(!mapper.irq());
+    }
+
+    #[test]
+    fn test_mmc3_a12_filter() {
+        let mut mapper = TxRom::new(banked_cartridge(4, 8, 8));
+
+        mapper.write(0xC000, 0);
+        mapper.write(0xE001, 0);
+        mapper.on_ppu_address(0x1000);
+        mapper.on_ppu_address(0x0000);
+        mapper.tick();
+        mapper.on_ppu_address(0x1000);
+
+        assert!(!mapper.irq());
+    }
+}
@@ -0,0 +1,661 @@
+// https://www.nesdev.org/wiki/MMC5
+
+use super::{mmc5_audio::Mmc5Audio, Mapper, Nametable};
+use crate::{
+    apu::ExpansionAudio,
+    cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
+    error::Error,
+    utils::{BitFlag, Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+const EXRAM_SIZE: usize = 1024;
+
+// PPU reads of a rendered scanline, counted from the third identical nametable read
+const BACKGROUND_FETCHES: u16 = 128;
+const SPRITE_FETCHES: u16 = 16;
+const PREFETCH_FETCHES: u16 = 8;
+
+// M2 cycles without PPU reads before the frame is considered over
+const IDLE_CYCLES: u8 = 3;
+
+#[derive(Debug)]
+pub struct Mmc5 {
+    cartridge: Cartridge,
+    prg_mode: u8,
+    prg_banks: [u8; 5],
+    prg_ram_protect: [u8; 2],
+    chr_mode: u8,
+    chr_banks_a: [u16; 8],
+    chr_banks_b: [u16; 4],
+    chr_upper: u8,
+    chr_last_b: bool,
+    exram: [u8; EXRAM_SIZE],
+    exram_mode: u8,
+    nametables: u8,
+    fill_tile: u8,
+    fill_attribute: u8,
+    split_control: u8,
+    split_scroll: u8,
+    split_bank: u8,
+    irq_target: u8,
+    irq_enabled: bool,
+    irq_pending: bool,
+    multiplicands: [u8; 2],
+    ppu_ctrl: u8,
+    last_address: u16,
+    matches: u8,
+    idle_cycles: u8,
+    in_frame: bool,
+    scanline: u8,
+    fetch: u16,
+    sprite_fetch: bool,
+    split_active: bool,
+    split_tile: u8,
+    split_y: u8,
+    ext_attribute: u8,
+    audio: Mmc5Audio,
+}
+
+impl Mmc5 {
+    pub fn new(cartridge: Cartridge) -> Self {
+        Self {
+            cartridge,
+            prg_mode: 3,
+            prg_banks: [0, 0, 0, 0, 0xFF],
+            prg_ram_protect: [0; 2],
+            chr_mode: 0,
+            chr_banks_a: [0; 8],
+            chr_banks_b: [0; 4],
+            chr_upper: 0,
+            chr_last_b: false,
+            exram: [0; EXRAM_SIZE],
+            exram_mode: 0,
+            nametables: 0,
+            fill_tile: 0,
+            fill_attribute: 0,
+            split_control: 0,
+            split_scroll: 0,
+            split_bank: 0,
+            irq_target: 0,
+            irq_enabled: false,
+            irq_pending: false,
+            multiplicands: [0xFF; 2],
+            ppu_ctrl: 0,
+            last_address: 0,
+            matches: 0,
+            idle_cycles: 0,
+            in_frame: false,
+            scanline: 0,
+            fetch: 0,
+            sprite_fetch: false,
+            split_active: false,
+            split_tile: 0,
+            split_y: 0,
+            ext_attribute: 0,
+            audio: Mmc5Audio::default(),
+        }
+    }
+
+    /// Returns the 8 KB bank mapped at `address` and whether it is ROM.
+    fn prg_bank(&self, address: u16) -> (u8, bool) {
+        if address < 0x8000 {
+            return (self.prg_banks[0], false);
+        }
+
+        let slot = ((address - 0x8000) >> 13) as u8;
+        let (register, bank) = match (self.prg_mode & 0b11, slot) {
+            (0, _) => (4, (self.prg_banks[4] & 0x7C) | slot),
+            (1 | 2, 0 | 1) => (2, (self.prg_banks[2] & 0x7E) | slot & 1),
+            (1, _) => (4, (self.prg_banks[4] & 0x7E) | slot & 1),
+            (2, 2) => (3, self.prg_banks[3]),
+            (_, slot) => (slot as usize + 1, self.prg_banks[slot as usize + 1]),
+        };
+
+        // $5117 always maps ROM
+        (
+            bank & 0x7F,
+            register == 4 || self.prg_banks[register].contains(7),
+        )
+    }
+
+    fn prg_ram_writable(&self) -> bool {
+        self.prg_ram_protect == [0b10, 0b01]
+    }
+
+    fn is_background_fetch(&self) -> bool {
+        self.in_frame && !self.sprite_fetch
+    }
+
+    fn chr_location(&self, address: u16) -> (ChrPage, u16) {
+        let slot = (address >> 10) as usize & 7;
+        let (bank, address) = if self.is_background_fetch() && self.split_active {
+            let fine_y = self.split_y as u16 & 7;
+            (
+                self.split_bank as usize * 4 + (slot & 3),
+                address & !7 | fine_y,
+            )
+        } else if self.is_background_fetch() && self.exram_mode == 1 {
+            let bank = (self.chr_upper as usize) << 6 | (self.ext_attribute & 0x3F) as usize;
+            (bank * 4 + (slot & 3), address)
+        } else {
+            (self.chr_bank(slot), address)
+        };
+        let count = match self.cartridge.header.chr_rom_pages {
+            0 => 8,
+            pages => pages as usize * 8,
+        };
+
+        (ChrPage::Index1(bank % count), address)
+    }
+
+    // https://www.nesdev.org/wiki/MMC5#CHR_mode_($5101)
+    fn chr_bank(&self, slot: usize) -> usize {
+        let use_b = match self.ppu_ctrl.contains(5) && self.in_frame {
+            true => !self.sprite_fetch,
+            false => self.chr_last_b,
+        };
+        let register = |index: usize| match use_b {
+            true => self.chr_banks_b[index & 3] as usize,
+            false => self.chr_banks_a[index] as usize,
+        };
+
+        match self.chr_mode & 0b11 {
+            0 => register(7) * 8 + slot,
+            1 => register(3 | slot & 4) * 4 + (slot & 3),
+            2 => register(slot | 1) * 2 + (slot & 1),
+            _ => register(slot),
+        }
+    }
+
+    fn nametable_mode(&self, address: u16) -> u8 {
+        self.nametables >> (((address >> 10) & 0b11) * 2) & 0b11
+    }
+
+    fn detect_scanline(&mut self) {
+        if self.in_frame {
+            self.scanline = self.scanline.wrapping_add(1);
+
+            if self.scanline == self.irq_target {
+                self.irq_pending = true;
+            }
+        } else {
+            self.in_frame = true;
+            self.irq_pending = false;
+            self.scanline = 0;
+        }
+    }
+
+    fn update_fetch(&mut self, address: u16) {
+        let fetch = self.fetch;
+        let sprites_end = BACKGROUND_FETCHES + SPRITE_FETCHES;
+
+        self.sprite_fetch = (BACKGROUND_FETCHES..sprites_end).contains(&fetch);
+
+        // tiles 0 and 1 of the next scanline are fetched at the end of the current one
+        let (column, next_line) = match fetch {
+            0..BACKGROUND_FETCHES => (fetch / 4 + 2, false),
+            _ if fetch >= sprites_end && fetch < sprites_end + PREFETCH_FETCHES => {
+                ((fetch - sprites_end) / 4, true)
+            }
+            _ => return,
+        };
+
+        if fetch % 4 != 0 {
+            return;
+        }
+
+        let column = column as u8;
+        let threshold = self.split_control & 0x1F;
+        let inside = match self.split_control.contains(6) {
+            true => column >= threshold,
+            false => column < threshold,
+        };
+        let scanline = self.scanline as u16 + next_line as u16;
+
+        self.split_active = self.split_control.contains(7) && self.exram_mode <= 1 && inside;
+        self.split_tile = column & 31;
+        self.split_y = ((self.split_scroll as u16 + scanline) % 240) as u8;
+
+        if self.exram_mode == 1 {
+            self.ext_attribute = self.exram[address as usize & 0x3FF];
+        }
+    }
+
+    fn write_exram(&mut self, address: u16, value: u8) {
+        let index = address as usize & 0x3FF;
+
+        match self.exram_mode {
+            0 | 1 if self.in_frame => self.exram[index] = value,
+            0 | 1 => self.exram[index] = 0,
+            2 => self.exram[index] = value,
+            _ => {}
+        }
+    }
+}
+
+impl Mapper for Mmc5 {
+    fn read(&self, address: u16) -> Option<u8> {
+        let product = self.multiplicands[0] as u16 * self.multiplicands[1] as u16;
+
+        Some(match address {
+            0x0000..=0x1FFF => {
+                let (page, address) = self.chr_location(address);
+                self.cartridge.read_chr(address, page)
+            }
+            0x5010 => self.audio.read_pcm_status(),
+            0x5015 => self.audio.read_status(),
+            0x5204 => (self.irq_pending as u8) << 7 | (self.in_frame as u8) << 6,
+            0x5205 => product as u8,
+            0x5206 => (product >> 8) as u8,
+            0x5C00..=0x5FFF if self.exram_mode >= 2 => self.exram[address as usize & 0x3FF],
+            0x6000..=0xFFFF => match self.prg_bank(address) {
+                (bank, true) => {
+                    let count = (self.cartridge.header.prg_rom_pages as usize * 2).max(1);
+                    let page = PrgPage::Index8(bank as usize % count);
+                    self.cartridge.read_prg_rom(address, page)
+                }
+                (bank, false) => self.cartridge.read_prg_ram_page(address, bank),
+            },
+            _ => return None,
+        })
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                let (page, address) = self.chr_location(address);
+                self.cartridge.write_chr(address, value, page)
+            }
+            0x5000..=0x5015 => self.audio.write_register(address, value),
+            0x5100 => self.prg_mode = value & 0b11,
+            0x5101 => self.chr_mode = value & 0b11,
+            0x5102..=0x5103 => self.prg_ram_protect[address as usize - 0x5102] = value & 0b11,
+            0x5104 => self.exram_mode = value & 0b11,
+            0x5105 => self.nametables = value,
+            0x5106 => self.fill_tile = value,
+            0x5107 => self.fill_attribute = value & 0b11,
+            0x5113..=0x5117 => self.prg_banks[address as usize - 0x5113] = value,
+            0x5120..=0x5127 => {
+                let bank = value as u16 | (self.chr_upper as u16) << 8;
+                self.chr_banks_a[address as usize - 0x5120] = bank;
+                self.chr_last_b = false;
+            }
+            0x5128..=0x512B => {
+                let bank = value as u16 | (self.chr_upper as u16) << 8;
+                self.chr_banks_b[address as usize - 0x5128] = bank;
+                self.chr_last_b = true;
+            }
+            0x5130 => self.chr_upper = value & 0b11,
+            0x5200 => self.split_control = value,
+            0x5201 => self.split_scroll = value,
+            0x5202 => self.split_bank = value,
+            0x5203 => self.irq_target = value,
+            0x5204 => self.irq_enabled = value.contains(7),
+            0x5205..=0x5206 => self.multiplicands[address as usize - 0x5205] = value,
+            0x5C00..=0x5FFF => self.write_exram(address, value),
+            0x6000..=0xFFFF => {
+                if let (bank, false) = self.prg_bank(address) {
+                    if self.prg_ram_writable() {
+                        self.cartridge.write_prg_ram_page(address, value, bank);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirroring {
+        self.cartridge.header.mirroring
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    fn on_ppu_address(&mut self, address: u16) {
+        let nametable = (0x2000..=0x2FFF).contains(&address);
+
+        self.idle_cycles = 0;
+        self.matches = match nametable && address == self.last_address {
+            true => self.matches.saturating_add(1),
+            false => 0,
+        };
+        self.last_address = address;
+
+        if self.matches == 2 {
+            self.detect_scanline();
+            self.fetch = 0;
+        } else {
+            self.fetch = self.fetch.saturating_add(1);
+        }
+
+        if self.in_frame {
+            self.update_fetch(address);
+        }
+    }
+
+    fn on_cpu_read(&mut self, address: u16) {
+        match address {
+            0x5010 => self.audio.acknowledge_pcm(),
+            0x5204 => self.irq_pending = false,
+            0x8000..=0xBFFF => {
+                let value = self.read(address).unwrap_or_default();
+                self.audio.on_prg_read(value);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_cpu_write(&mut self, address: u16, value: u8) {
+        if address < 0x4000 && address & 0x0007 == 0 {
+            self.ppu_ctrl = value;
+        }
+    }
+
+    fn get_nametable(&self, address: u16) -> Nametable {
+        let attribute = address & 0x3FF >= 0x3C0;
+
+        if self.is_background_fetch() && (self.split_active || self.exram_mode == 1 && attribute) {
+            return Nametable::Mapper;
+        }
+
+        match self.nametable_mode(address) {
+            page @ (0 | 1) => Nametable::Ciram(page),
+            _ => Nametable::Mapper,
+        }
+    }
+
+    fn read_nametable(&self, address: u16) -> u8 {
+        let offset = address as usize & 0x3FF;
+        let attribute = offset >= 0x3C0;
+
+        if self.is_background_fetch() && self.split_active {
+            let (row, column) = (self.split_y as usize / 8, self.split_tile as usize);
+
+            return match attribute {
+                false => self.exram[row * 32 + column],
+                true => {
+                    let byte = self.exram[0x3C0 + row / 4 * 8 + column / 4];
+                    let shift = (row & 2) * 2 + (column & 2);
+                    (byte >> shift & 0b11) * 0x55
+                }
+            };
+        }
+
+        if self.is_background_fetch() && self.exram_mode == 1 && attribute {
+            return (self.ext_attribute >> 6) * 0x55;
+        }
+
+        match self.nametable_mode(address) {
+            2 if self.exram_mode <= 1 => self.exram[offset],
+            3 if attribute => self.fill_attribute * 0x55,
+            3 => self.fill_tile,
+            _ => 0,
+        }
+    }
+
+    fn write_nametable(&mut self, address: u16, value: u8) {
+        if self.nametable_mode(address) == 2 && self.exram_mode <= 1 {
+            self.exram[address as usize & 0x3FF] = value;
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending && self.irq_enabled || self.audio.irq()
+    }
+
+    fn expansion_audio(&self) -> Option<&dyn ExpansionAudio> {
+        Some(&self.audio)
+    }
+}
+
+impl Clock for Mmc5 {
+    fn tick(&mut self) {
+        self.idle_cycles = self.idle_cycles.saturating_add(1);
+
+        if self.idle_cycles >= IDLE_CYCLES {
+            self.in_frame = false;
+            self.sprite_fetch = false;
+            self.split_active = false;
+        }
+
+        self.audio.tick();
+    }
+}
+
+impl Reset for Mmc5 {
+    fn reset(&mut self) {
+        let cartridge = std::mem::take(&mut self.cartridge);
+        *self = Self::new(cartridge);
+    }
+}
+
+impl Snapshot for Mmc5 {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.prg_mode);
+        state.write_bytes(&self.prg_banks);
+        state.write_bytes(&self.prg_ram_protect);
+        state.write_u8(self.chr_mode);
+
+        for bank in self.chr_banks_a.iter().chain(&self.chr_banks_b) {
+            state.write_u16(*bank);
+        }
+
+        state.write_u8(self.chr_upper);
+        state.write_bool(self.chr_last_b);
+        state.write_bytes(&self.exram);
+        state.write_u8(self.exram_mode);
+        state.write_u8(self.nametables);
+        state.write_u8(self.fill_tile);
+        state.write_u8(self.fill_attribute);
+        state.write_u8(self.split_control);
+        state.write_u8(self.split_scroll);
+        state.write_u8(self.split_bank);
+        state.write_u8(self.irq_target);
+        state.write_bool(self.irq_enabled);
+        state.write_bool(self.irq_pending);
+        state.write_bytes(&self.multiplicands);
+        state.write_u8(self.ppu_ctrl);
+        state.write_u16(self.last_address);
+        state.write_u8(self.matches);
+        state.write_u8(self.idle_cycles);
+        state.write_bool(self.in_frame);
+        state.write_u8(self.scanline);
+        state.write_u16(self.fetch);
+        state.write_bool(self.sprite_fetch);
+        state.write_bool(self.split_active);
+        state.write_u8(self.split_tile);
+        state.write_u8(self.split_y);
+        state.write_u8(self.ext_attribute);
+        self.audio.save(state);
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.prg_mode = state.read_u8()? & 0b11;
+        state.read_bytes(&mut self.prg_banks)?;
+        state.read_bytes(&mut self.prg_ram_protect)?;
+        self.chr_mode = state.read_u8()? & 0b11;
+
+        for bank in self.chr_banks_a.iter_mut().chain(&mut self.chr_banks_b) {
+            *bank = state.read_u16()?;
+        }
+
+        self.chr_upper = state.read_u8()?;
+        self.chr_last_b = state.read_bool()?;
+        state.read_bytes(&mut self.exram)?;
+        self.exram_mode = state.read_u8()? & 0b11;
+        self.nametables = state.read_u8()?;
+        self.fill_tile = state.read_u8()?;
+        self.fill_attribute = state.read_u8()? & 0b11;
+        self.split_control = state.read_u8()?;
+        self.split_scroll = state.read_u8()?;
+        self.split_bank = state.read_u8()?;
+        self.irq_target = state.read_u8()?;
+        self.irq_enabled = state.read_bool()?;
+        self.irq_pending = state.read_bool()?;
+        state.read_bytes(&mut self.multiplicands)?;
+        self.ppu_ctrl = state.read_u8()?;
+        self.last_address = state.read_u16()?;
+        self.matches = state.read_u8()?;
+        self.idle_cycles = state.read_u8()?;
+        self.in_frame = state.read_bool()?;
+        self.scanline = state.read_u8()?;
+        self.fetch = state.read_u16()?;
+        self.sprite_fetch = state.read_bool()?;
+        self.split_active = state.read_bool()?;
+        self.split_tile = state.read_u8()? & 31;
+        self.split_y = state.read_u8()? % 240;
+        self.ext_attribute = state.read_u8()?;
+        self.audio.load(state)?;
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Mmc5;
+    use crate::{
+        mappers::{Mapper, Nametable},
+        utils::{test::banked_cartridge, Clock},
+    };
+
+    fn mmc5() -> Mmc5 {
+        let mut cartridge = banked_cartridge(5, 8, 32);
+        cartridge.prg_ram = vec![0; 0x10000];
+        Mmc5::new(cartridge)
+    }
+
+    /// Feeds the three identical nametable reads around a scanline boundary,
+    /// the last one being the first tile fetch of the new scanline.
+    fn start_scanline(mapper: &mut Mmc5, address: u16) {
+        mapper.on_ppu_address(0x1000);
+
+        for _ in 0..3 {
+            mapper.on_ppu_address(address);
+        }
+    }
+
+    #[test]
+    fn test_mmc5_prg_modes() {
+        let mut mapper = mmc5();
+
+        assert_eq!(mapper.read(0xE000), Some(15));
+
+        mapper.write(0x5102, 0b10);
+        mapper.write(0x5103, 0b01);
+        mapper.write(0x5113, 3);
+        mapper.write(0x6000, 0x42);
+        mapper.write(0x5114, 0x05); // RAM page 5 at $8000
+        mapper.write(0x8000, 0x24);
+        mapper.write(0x5115, 0x89);
+
+        assert_eq!(mapper.read(0x6000), Some(0x42));
+        assert_eq!(mapper.read(0x8000), Some(0x24));
+        assert_eq!(mapper.read(0xA000), Some(9));
+
+        mapper.write(0x5100, 1);
+
+        assert_eq!(mapper.read(0x8000), Some(8));
+        assert_eq!(mapper.read(0xA000), Some(9));
+        assert_eq!(mapper.read(0xC000), Some(14));
+
+        mapper.write(0x5100, 0);
+
+        assert_eq!(mapper.read(0x8000), Some(12));
+    }
+
+    #[test]
+    fn test_mmc5_large_chr() {
+        let mut cartridge = banked_cartridge(5, 8, 128);
+        // 4 KB fill so that a truncated bank number reads a different value
+        cartridge.chr_rom = (0..1024)
+            .flat_map(|bank| vec![(bank >> 2) as u8; 0x400])
+            .collect();
+
+        let mut mapper = Mmc5::new(cartridge);
+
+        mapper.write(0x5101, 3);
+        mapper.write(0x5130, 3);
+        mapper.write(0x5127, 0xFF);
+
+        assert_eq!(mapper.read(0x1C00), Some(0xFF));
+    }
+
+    #[test]
+    fn test_mmc5_scanline_irq() {
+        let mut mapper = mmc5();
+
+        mapper.write(0x5203, 2);
+        mapper.write(0x5204, 0x80);
+
+        start_scanline(&mut mapper, 0x2000);
+        start_scanline(&mut mapper, 0x2000);
+
+        assert_eq!(mapper.read(0x5204), Some(0x40));
+
+        start_scanline(&mut mapper, 0x2000);
+
+        assert!(mapper.irq());
+
+        mapper.on_cpu_read(0x5204);
+
+        assert!(!mapper.irq());
+
+        for _ in 0..3 {
+            mapper.tick();
+        }
+
+        assert_eq!(mapper.read(0x5204), Some(0));
+    }
+
+    #[test]
+    fn test_mmc5_nametables() {
+        let mut mapper = mmc5();
+
+        mapper.write(0x5105, 0b11_10_01_00);
+        mapper.write(0x5106, 0x33);
+        mapper.write(0x5107, 2);
+        mapper.write_nametable(0x2805, 0x77);
+
+        assert_eq!(mapper.get_nametable(0x2000), Nametable::Ciram(0));
+        assert_eq!(mapper.get_nametable(0x2400), Nametable::Ciram(1));
+        assert_eq!(mapper.get_nametable(0x2800), Nametable::Mapper);
+        assert_eq!(mapper.read_nametable(0x2C10), 0x33);
+        assert_eq!(mapper.read_nametable(0x2FC0), 0xAA);
+
+        mapper.write(0x5104, 2);
+
+        assert_eq!(mapper.read(0x5C05), Some(0x77));
+    }
+
+    #[test]
+    fn test_mmc5_extended_attributes() {
+        let mut mapper = mmc5();
+
+        mapper.write(0x5104, 2);
+        mapper.write(0x5C21, 0b11_000101); // palette 3, 4 KB bank 5
+        mapper.write(0x5104, 1);
+        mapper.write(0x5130, 1);
+
+        start_scanline(&mut mapper, 0x2021);
+
+        assert_eq!(mapper.read_nametable(0x23C0), 0xFF);
+        assert_eq!(mapper.read(0x0400), Some(((1 << 6 | 5) * 4 + 1) as u8));
+    }
+
+    #[test]
+    fn test_mmc5_multiplier() {
+        let mut mapper = mmc5();
+
+        mapper.write(0x5205, 200);
+        mapper.write(0x5206, 150);
+
+        assert_eq!(mapper.read(0x5205), Some((30_000 & 0xFF) as u8));
+        assert_eq!(mapper.read(0x5206), Some((30_000 >> 8) as u8));
+    }
+}
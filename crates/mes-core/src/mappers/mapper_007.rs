@@ -0,0 +1,125 @@
+// https://www.nesdev.org/wiki/AxROM
+
+use super::Mapper;
+use crate::{
+    cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
+    error::Error,
+    utils::{BitFlag, Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+#[derive(Debug)]
+pub struct AxRom {
+    cartridge: Cartridge,
+    prg_bank: u8,
+    mirroring: Mirroring,
+}
+
+impl AxRom {
+    pub fn new(cartridge: Cartridge) -> Self {
+        Self {
+            cartridge,
+            prg_bank: 0,
+            mirroring: Mirroring::OneScreenLower,
+        }
+    }
+
+    fn prg_bank_count(&self) -> usize {
+        (self.cartridge.header.prg_rom_pages as usize / 2).max(1)
+    }
+}
+
+impl Mapper for AxRom {
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
+            0x0000..=0x1FFF => self.cartridge.read_chr(address, ChrPage::Index8(0)),
+            0x8000..=0xFFFF => self.cartridge.read_prg_rom(
+                address,
+                PrgPage::Index32(self.prg_bank as usize % self.prg_bank_count()),
+            ),
+            _ => return None,
+        })
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.cartridge.write_chr(address, value, ChrPage::Index8(0)),
+            0x8000..=0xFFFF => {
+                self.prg_bank = value & 0b111;
+                self.mirroring = match value.contains(4) {
+                    true => Mirroring::OneScreenUpper,
+                    false => Mirroring::OneScreenLower,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+}
+
+impl Clock for AxRom {}
+
+impl Reset for AxRom {
+    fn reset(&mut self) {
+        self.prg_bank = 0;
+        self.mirroring = Mirroring::OneScreenLower;
+    }
+}
+
+impl Snapshot for AxRom {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.prg_bank);
+        self.mirroring.save(state);
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.prg_bank = state.read_u8()?;
+        self.mirroring.load(state)?;
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AxRom;
+    use crate::{
+        cartridge::{Cartridge, Header, Mirroring},
+        mappers::Mapper,
+    };
+
+    #[test]
+    fn test_axrom_banking() {
+        let cartridge = Cartridge {
+            header: Header {
+                prg_rom_pages: 8,
+                chr_rom_pages: 0,
+                mapper: 7,
+                ..Cartridge::default().header
+            },
+            prg_rom: (0..4).flat_map(|bank| vec![bank; 0x8000]).collect(),
+            chr_ram: vec![0; 0x2000],
+            ..Default::default()
+        };
+
+        let mut mapper = AxRom::new(cartridge);
+
+        assert_eq!(mapper.read(0xFFFF), Some(0));
+        assert_eq!(mapper.get_mirroring(), Mirroring::OneScreenLower);
+
+        mapper.write(0x8000, 0b0001_0010);
+
+        assert_eq!(mapper.read(0x8000), Some(2));
+        assert_eq!(mapper.get_mirroring(), Mirroring::OneScreenUpper);
+    }
+}
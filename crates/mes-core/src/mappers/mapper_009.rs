@@ -0,0 +1,245 @@
+// https://www.nesdev.org/wiki/MMC2
+
+use super::Mapper;
+use crate::{
+    cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
+    error::Error,
+    utils::{BitFlag, Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+const LATCH_FD: u8 = 0;
+const LATCH_FE: u8 = 1;
+
+/// CHR banking shared by MMC2 and MMC4, each pattern table has a $FD and a $FE bank
+/// selected by the last $FD/$FE tile fetched from it.
+#[derive(Debug)]
+pub(super) struct ChrLatch {
+    banks: [u8; 4],
+    latches: [u8; 2],
+}
+
+impl Default for ChrLatch {
+    fn default() -> Self {
+        Self {
+            banks: [0; 4],
+            latches: [LATCH_FE; 2],
+        }
+    }
+}
+
+impl ChrLatch {
+    pub(super) fn get_page(&self, address: u16, bank_count: usize) -> ChrPage {
+        let table = (address >> 12) as usize & 1;
+        let bank = self.banks[table * 2 + self.latches[table] as usize];
+        ChrPage::Index4(bank as usize % bank_count)
+    }
+
+    // 0xB000: $0000 FD, 0xC000: $0000 FE, 0xD000: $1000 FD, 0xE000: $1000 FE
+    pub(super) fn write_bank(&mut self, address: u16, value: u8) {
+        self.banks[(address >> 12) as usize - 0xB] = value & 0b1_1111;
+    }
+
+    /// MMC2 only triggers the first latch on $0FD8 and $0FE8, MMC4 uses whole tile rows.
+    pub(super) fn update(&mut self, address: u16, exact: bool) {
+        match address {
+            0x0FD8 => self.latches[0] = LATCH_FD,
+            0x0FE8 => self.latches[0] = LATCH_FE,
+            0x0FD9..=0x0FDF if !exact => self.latches[0] = LATCH_FD,
+            0x0FE9..=0x0FEF if !exact => self.latches[0] = LATCH_FE,
+            0x1FD8..=0x1FDF => self.latches[1] = LATCH_FD,
+            0x1FE8..=0x1FEF => self.latches[1] = LATCH_FE,
+            _ => {}
+        }
+    }
+}
+
+impl Snapshot for ChrLatch {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_bytes(&self.banks);
+        state.write_bytes(&self.latches);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        state.read_bytes(&mut self.banks)?;
+        state.read_bytes(&mut self.latches)?;
+
+        match self.latches.iter().all(|&latch| latch <= LATCH_FE) {
+            true => Ok(()),
+            false => Err(Error::InvalidSaveState),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PxRom {
+    cartridge: Cartridge,
+    prg_bank: u8,
+    chr: ChrLatch,
+    mirroring: Mirroring,
+}
+
+impl PxRom {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let mirroring = cartridge.header.mirroring;
+
+        Self {
+            cartridge,
+            prg_bank: 0,
+            chr: ChrLatch::default(),
+            mirroring,
+        }
+    }
+
+    fn prg_bank_count(&self) -> usize {
+        (self.cartridge.header.prg_rom_pages as usize * 2).max(1)
+    }
+
+    fn chr_bank_count(&self) -> usize {
+        (self.cartridge.header.chr_rom_pages as usize * 2).max(2)
+    }
+
+    fn prg_page(&self, address: u16) -> PrgPage {
+        let count = self.prg_bank_count();
+
+        match address {
+            0x8000..=0x9FFF => PrgPage::Index8(self.prg_bank as usize % count),
+            0xA000..=0xBFFF => PrgPage::Index8(count.saturating_sub(3)),
+            0xC000..=0xDFFF => PrgPage::Index8(count.saturating_sub(2)),
+            _ => PrgPage::Index8(count - 1),
+        }
+    }
+}
+
+impl Mapper for PxRom {
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
+            0x0000..=0x1FFF => self
+                .cartridge
+                .read_chr(address, self.chr.get_page(address, self.chr_bank_count())),
+            0x6000..=0x7FFF => self.cartridge.read_prg_ram(address),
+            0x8000..=0xFFFF => self.cartridge.read_prg_rom(address, self.prg_page(address)),
+            _ => return None,
+        })
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF => self.cartridge.write_prg_ram(address, value),
+            0xA000..=0xAFFF => self.prg_bank = value & 0b1111,
+            0xB000..=0xEFFF => self.chr.write_bank(address, value),
+            0xF000..=0xFFFF => {
+                self.mirroring = match value.contains(0) {
+                    true => Mirroring::Horizontal,
+                    false => Mirroring::Vertical,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn fetch_chr(&mut self, address: u16) -> u8 {
+        let value = self.read(address).unwrap_or_default();
+        self.chr.update(address, true);
+        value
+    }
+
+    fn get_mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+}
+
+impl Clock for PxRom {}
+
+impl Reset for PxRom {
+    fn reset(&mut self) {
+        self.prg_bank = 0;
+        self.chr = ChrLatch::default();
+        self.mirroring = self.cartridge.header.mirroring;
+    }
+}
+
+impl Snapshot for PxRom {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.prg_bank);
+        self.chr.save(state);
+        self.mirroring.save(state);
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.prg_bank = state.read_u8()?;
+        self.chr.load(state)?;
+        self.mirroring.load(state)?;
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChrLatch, PxRom, LATCH_FE};
+    use crate::{
+        cartridge::{Cartridge, Header},
+        error::Error,
+        mappers::Mapper,
+        utils::{Snapshot, SnapshotReader, SnapshotWriter},
+    };
+
+    #[test]
+    fn test_mmc2_chr_latch() {
+        let cartridge = Cartridge {
+            header: Header {
+                prg_rom_pages: 8,
+                chr_rom_pages: 4,
+                mapper: 9,
+                ..Cartridge::default().header
+            },
+            prg_rom: (0..16).flat_map(|bank| vec![bank; 0x2000]).collect(),
+            chr_rom: (0..8).flat_map(|bank| vec![bank; 0x1000]).collect(),
+            ..Default::default()
+        };
+
+        let mut mapper = PxRom::new(cartridge);
+
+        mapper.write(0xA000, 5);
+        mapper.write(0xB000, 1);
+        mapper.write(0xC000, 2);
+        mapper.write(0xD000, 3);
+        mapper.write(0xE000, 4);
+
+        assert_eq!(mapper.read(0x8000), Some(5));
+        assert_eq!(mapper.read(0xA000), Some(13));
+        assert_eq!(mapper.read(0x0000), Some(2));
+        assert_eq!(mapper.read(0x1000), Some(4));
+        assert_eq!(mapper.fetch_chr(0x0FD8), 2); // latch switches after the fetch
+        assert_eq!(mapper.read(0x0000), Some(1));
+
+        mapper.fetch_chr(0x0FD9);
+        mapper.fetch_chr(0x0FE9);
+
+        assert_eq!(mapper.read(0x0000), Some(1));
+
+        mapper.fetch_chr(0x1FDA);
+
+        assert_eq!(mapper.read(0x1000), Some(3));
+    }
+
+    #[test]
+    fn test_chr_latch_invalid_save_state() {
+        let mut state = SnapshotWriter::default();
+        state.write_bytes(&[0; 4]);
+        state.write_bytes(&[LATCH_FE, 2]);
+        let bytes = state.into_bytes();
+
+        let result = ChrLatch::default().load(&mut SnapshotReader::new(&bytes));
+
+        assert_eq!(result, Err(Error::InvalidSaveState));
+    }
+}
@@ -0,0 +1,166 @@
+// https://www.nesdev.org/wiki/MMC4
+
+use super::{mapper_009::ChrLatch, Mapper};
+use crate::{
+    cartridge::{Cartridge, Mirroring, PrgPage},
+    error::Error,
+    utils::{BitFlag, Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+#[derive(Debug)]
+pub struct FxRom {
+    cartridge: Cartridge,
+    prg_bank: u8,
+    chr: ChrLatch,
+    mirroring: Mirroring,
+}
+
+impl FxRom {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let mirroring = cartridge.header.mirroring;
+
+        Self {
+            cartridge,
+            prg_bank: 0,
+            chr: ChrLatch::default(),
+            mirroring,
+        }
+    }
+
+    fn chr_bank_count(&self) -> usize {
+        (self.cartridge.header.chr_rom_pages as usize * 2).max(2)
+    }
+}
+
+impl Mapper for FxRom {
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
+            0x0000..=0x1FFF => self
+                .cartridge
+                .read_chr(address, self.chr.get_page(address, self.chr_bank_count())),
+            0x6000..=0x7FFF => self.cartridge.read_prg_ram(address),
+            0x8000..=0xBFFF => {
+                let bank =
+                    self.prg_bank as usize % self.cartridge.header.prg_rom_pages.max(1) as usize;
+                self.cartridge.read_prg_rom(address, PrgPage::Index16(bank))
+            }
+            0xC000..=0xFFFF => self.cartridge.read_prg_rom(address, PrgPage::Last16),
+            _ => return None,
+        })
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF => self.cartridge.write_prg_ram(address, value),
+            0xA000..=0xAFFF => self.prg_bank = value & 0b1111,
+            0xB000..=0xEFFF => self.chr.write_bank(address, value),
+            0xF000..=0xFFFF => {
+                self.mirroring = match value.contains(0) {
+                    true => Mirroring::Horizontal,
+                    false => Mirroring::Vertical,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn fetch_chr(&mut self, address: u16) -> u8 {
+        let value = self.read(address).unwrap_or_default();
+        self.chr.update(address, false);
+        value
+    }
+
+    fn get_mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+}
+
+impl Clock for FxRom {}
+
+impl Reset for FxRom {
+    fn reset(&mut self) {
+        self.prg_bank = 0;
+        self.chr = ChrLatch::default();
+        self.mirroring = self.cartridge.header.mirroring;
+    }
+}
+
+impl Snapshot for FxRom {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.prg_bank);
+        self.chr.save(state);
+        self.mirroring.save(state);
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.prg_bank = state.read_u8()?;
+        self.chr.load(state)?;
+        self.mirroring.load(state)?;
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FxRom;
+    use crate::{
+        cartridge::{Cartridge, Header, Mirroring},
+        mappers::Mapper,
+    };
+
+    #[test]
+    fn test_mmc4_chr_latch() {
+        let cartridge = Cartridge {
+            header: Header {
+                prg_rom_pages: 8,
+                chr_rom_pages: 4,
+                mapper: 10,
+                ..Cartridge::default().header
+            },
+            prg_rom: (0..8).flat_map(|bank| vec![bank; 0x4000]).collect(),
+            chr_rom: (0..8).flat_map(|bank| vec![bank; 0x1000]).collect(),
+            ..Default::default()
+        };
+
+        let mut mapper = FxRom::new(cartridge);
+
+        mapper.write(0xA000, 5);
+        mapper.write(0xB000, 1);
+        mapper.write(0xC000, 2);
+        mapper.write(0xD000, 3);
+        mapper.write(0xE000, 4);
+        mapper.write(0xF000, 1);
+
+        assert_eq!(mapper.read(0x8000), Some(5));
+        assert_eq!(mapper.read(0xBFFF), Some(5));
+        assert_eq!(mapper.read(0xC000), Some(7));
+        assert_eq!(mapper.get_mirroring(), Mirroring::Horizontal);
+        assert_eq!(mapper.read(0x0000), Some(2));
+        assert_eq!(mapper.read(0x1000), Some(4));
+        assert_eq!(mapper.fetch_chr(0x0FDB), 2); // latch switches after the fetch
+        assert_eq!(mapper.read(0x0000), Some(1));
+
+        mapper.fetch_chr(0x0FEF);
+
+        assert_eq!(mapper.read(0x0000), Some(2));
+
+        mapper.fetch_chr(0x1FDF);
+
+        assert_eq!(mapper.read(0x1000), Some(3));
+
+        mapper.fetch_chr(0x0FF0);
+        mapper.fetch_chr(0x1FE7);
+
+        assert_eq!(mapper.read(0x0000), Some(2));
+        assert_eq!(mapper.read(0x1000), Some(3));
+    }
+}
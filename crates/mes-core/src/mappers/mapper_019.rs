@@ -0,0 +1,337 @@
+// https://www.nesdev.org/wiki/INES_Mapper_019
+
+use super::{namco163_audio::Namco163Audio, Mapper, Nametable};
+use crate::{
+    apu::ExpansionAudio,
+    cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
+    error::Error,
+    utils::{BitFlag, Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+const SOUND_RAM_SIZE: usize = 128;
+const IRQ_COUNTER_MAX: u16 = 0x7FFF;
+
+// banks from $E0 select the console VRAM instead of CHR ROM
+const CIRAM_BANKS: u8 = 0xE0;
+
+#[derive(Debug)]
+pub struct Namco163 {
+    cartridge: Cartridge,
+    chr_banks: [u8; 8],
+    nametable_banks: [u8; 4],
+    prg_banks: [u8; 3],
+    sound_address: u8,
+    irq_counter: u16,
+    irq_enabled: bool,
+    interrupt: bool,
+    /// Offset of the internal sound RAM, stored after the PRG RAM so both share the battery.
+    sound_ram: usize,
+    audio: Namco163Audio,
+}
+
+impl Namco163 {
+    pub fn new(mut cartridge: Cartridge) -> Self {
+        let sound_ram = cartridge.prg_ram.len();
+
+        cartridge.prg_ram.resize(sound_ram + SOUND_RAM_SIZE, 0);
+
+        Self {
+            cartridge,
+            chr_banks: [0; 8],
+            nametable_banks: [0; 4],
+            prg_banks: [0; 3],
+            sound_address: 0,
+            irq_counter: 0,
+            irq_enabled: false,
+            interrupt: false,
+            sound_ram,
+            audio: Namco163Audio::default(),
+        }
+    }
+
+    fn prg_page(&self, address: u16) -> PrgPage {
+        let count = (self.cartridge.header.prg_rom_pages as usize * 2).max(1);
+        let bank = match address {
+            0x8000..=0x9FFF => (self.prg_banks[0] & 0x3F) as usize,
+            0xA000..=0xBFFF => (self.prg_banks[1] & 0x3F) as usize,
+            0xC000..=0xDFFF => (self.prg_banks[2] & 0x3F) as usize,
+            _ => count - 1,
+        };
+
+        PrgPage::Index8(bank % count)
+    }
+
+    fn chr_page(&self, bank: u8) -> ChrPage {
+        let count = match self.cartridge.header.chr_rom_pages {
+            0 => 8,
+            pages => pages as usize * 8,
+        };
+
+        ChrPage::Index1(bank as usize % count)
+    }
+
+    fn sound_ram_index(&self) -> usize {
+        self.sound_ram + (self.sound_address & 0x7F) as usize
+    }
+
+    fn increment_sound_address(&mut self) {
+        if self.sound_address.contains(7) {
+            self.sound_address = 0x80 | (self.sound_address.wrapping_add(1) & 0x7F);
+        }
+    }
+
+    /// $F800 also holds the write protection of the four 2 KB PRG RAM windows.
+    fn prg_ram_writable(&self, address: u16) -> bool {
+        let window = ((address - 0x6000) >> 11) as u8;
+        self.sound_address & 0xF0 == 0x40 && !self.sound_address.contains(window)
+    }
+
+    fn acknowledge(&mut self) {
+        self.interrupt = false;
+    }
+}
+
+impl Mapper for Namco163 {
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
+            0x0000..=0x1FFF => {
+                let bank = self.chr_banks[address as usize >> 10];
+                self.cartridge.read_chr(address, self.chr_page(bank))
+            }
+            0x4800..=0x4FFF => self.cartridge.prg_ram[self.sound_ram_index()],
+            0x5000..=0x57FF => self.irq_counter as u8,
+            0x5800..=0x5FFF => (self.irq_counter >> 8) as u8 | (self.irq_enabled as u8) << 7,
+            0x6000..=0x7FFF if self.sound_ram > 0 => self.cartridge.read_prg_ram(address),
+            0x8000..=0xFFFF => self.cartridge.read_prg_rom(address, self.prg_page(address)),
+            _ => return None,
+        })
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                let page = self.chr_page(self.chr_banks[address as usize >> 10]);
+                self.cartridge.write_chr(address, value, page)
+            }
+            0x4800..=0x4FFF => {
+                let index = self.sound_ram_index();
+                self.cartridge.write_prg_ram_offset(index, value);
+                self.increment_sound_address();
+            }
+            0x5000..=0x57FF => {
+                self.irq_counter = (self.irq_counter & 0x7F00) | value as u16;
+                self.acknowledge();
+            }
+            0x5800..=0x5FFF => {
+                self.irq_counter = (self.irq_counter & 0x00FF) | ((value & 0x7F) as u16) << 8;
+                self.irq_enabled = value.contains(7);
+                self.acknowledge();
+            }
+            0x6000..=0x7FFF if self.sound_ram > 0 && self.prg_ram_writable(address) => {
+                self.cartridge.write_prg_ram(address, value)
+            }
+            0x8000..=0xBFFF => self.chr_banks[(address as usize - 0x8000) >> 11] = value,
+            0xC000..=0xDFFF => self.nametable_banks[(address as usize - 0xC000) >> 11] = value,
+            0xE000..=0xE7FF => {
+                self.prg_banks[0] = value;
+                self.audio.set_disabled(value.contains(6));
+            }
+            0xE800..=0xEFFF => self.prg_banks[1] = value,
+            0xF000..=0xF7FF => self.prg_banks[2] = value,
+            0xF800..=0xFFFF => self.sound_address = value,
+            _ => {}
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirroring {
+        self.cartridge.header.mirroring
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    fn on_cpu_read(&mut self, address: u16) {
+        if (0x4800..=0x4FFF).contains(&address) {
+            self.increment_sound_address();
+        }
+    }
+
+    fn get_nametable(&self, address: u16) -> Nametable {
+        match self.nametable_banks[(address as usize >> 10) & 0b11] {
+            bank if bank >= CIRAM_BANKS => Nametable::Ciram(bank & 1),
+            _ => Nametable::Mapper,
+        }
+    }
+
+    fn read_nametable(&self, address: u16) -> u8 {
+        let bank = self.nametable_banks[(address as usize >> 10) & 0b11];
+        self.cartridge.read_chr(address, self.chr_page(bank))
+    }
+
+    fn write_nametable(&mut self, address: u16, value: u8) {
+        let page = self.chr_page(self.nametable_banks[(address as usize >> 10) & 0b11]);
+        self.cartridge.write_chr(address, value, page)
+    }
+
+    fn get_chr_ciram(&self, address: u16) -> Option<u8> {
+        let bank = self.chr_banks[address as usize >> 10];
+        // $E800 bits 6 and 7 force CHR ROM for the low and high pattern tables
+        let rom_only = self.prg_banks[1].contains(6 + (address >> 12) as u8);
+
+        (bank >= CIRAM_BANKS && !rom_only).then_some(bank & 1)
+    }
+
+    fn irq(&self) -> bool {
+        self.interrupt
+    }
+
+    fn expansion_audio(&self) -> Option<&dyn ExpansionAudio> {
+        Some(&self.audio)
+    }
+}
+
+impl Clock for Namco163 {
+    fn tick(&mut self) {
+        if self.irq_enabled && self.irq_counter < IRQ_COUNTER_MAX {
+            self.irq_counter += 1;
+
+            if self.irq_counter == IRQ_COUNTER_MAX {
+                self.interrupt = true;
+            }
+        }
+
+        let ram = &mut self.cartridge.prg_ram[self.sound_ram..];
+        self.audio.clock(ram);
+    }
+}
+
+impl Reset for Namco163 {
+    fn reset(&mut self) {
+        self.chr_banks = [0; 8];
+        self.nametable_banks = [0; 4];
+        self.prg_banks = [0; 3];
+        self.sound_address = 0;
+        self.irq_counter = 0;
+        self.irq_enabled = false;
+        self.interrupt = false;
+        self.audio = Namco163Audio::default();
+    }
+}
+
+impl Snapshot for Namco163 {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_bytes(&self.chr_banks);
+        state.write_bytes(&self.nametable_banks);
+        state.write_bytes(&self.prg_banks);
+        state.write_u8(self.sound_address);
+        state.write_u16(self.irq_counter);
+        state.write_bool(self.irq_enabled);
+        state.write_bool(self.interrupt);
+        self.audio.save(state);
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        state.read_bytes(&mut self.chr_banks)?;
+        state.read_bytes(&mut self.nametable_banks)?;
+        state.read_bytes(&mut self.prg_banks)?;
+        self.sound_address = state.read_u8()?;
+        self.irq_counter = state.read_u16()? & IRQ_COUNTER_MAX;
+        self.irq_enabled = state.read_bool()?;
+        self.interrupt = state.read_bool()?;
+        self.audio.load(state)?;
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Namco163;
+    use crate::{
+        mappers::{Mapper, Nametable},
+        utils::{test::banked_cartridge, Clock},
+    };
+
+    fn namco163() -> Namco163 {
+        let mut cartridge = banked_cartridge(19, 8, 32);
+        cartridge.header.battery = true;
+        Namco163::new(cartridge)
+    }
+
+    #[test]
+    fn test_namco163_banking() {
+        let mut mapper = namco163();
+
+        mapper.write(0xE000, 0x03);
+        mapper.write(0xF000, 0x07);
+        mapper.write(0x8800, 0x21);
+        mapper.write(0x9800, 0xE1);
+        mapper.write(0xC800, 0x42);
+        mapper.write(0xD000, 0xE0);
+
+        assert_eq!(mapper.read(0x8000), Some(3));
+        assert_eq!(mapper.read(0xC000), Some(7));
+        assert_eq!(mapper.read(0xE000), Some(15));
+        assert_eq!(mapper.read(0x0400), Some(0x21));
+        assert_eq!(mapper.get_chr_ciram(0x0400), None);
+        assert_eq!(mapper.get_chr_ciram(0x0C00), Some(1));
+        assert_eq!(mapper.get_nametable(0x2400), Nametable::Mapper);
+        assert_eq!(mapper.read_nametable(0x2400), 0x42);
+        assert_eq!(mapper.get_nametable(0x2800), Nametable::Ciram(0));
+
+        mapper.write(0xE800, 0x40);
+
+        assert_eq!(mapper.get_chr_ciram(0x0C00), None);
+        assert_eq!(mapper.read(0x0C00), Some(0xE1));
+    }
+
+    #[test]
+    fn test_namco163_irq_counter() {
+        let mut mapper = namco163();
+
+        mapper.write(0x5000, 0xFE);
+        mapper.write(0x5800, 0xFF);
+        mapper.tick();
+
+        assert!(mapper.irq());
+        assert_eq!(mapper.read(0x5800), Some(0xFF));
+
+        mapper.tick();
+        mapper.write(0x5800, 0xFF);
+
+        assert!(!mapper.irq());
+        assert_eq!(mapper.read(0x5000), Some(0xFF));
+    }
+
+    #[test]
+    fn test_namco163_sound_ram() {
+        let mut mapper = namco163();
+
+        mapper.write(0xF800, 0xFF);
+        mapper.write(0x4800, 0x11);
+        mapper.write(0x4800, 0x22);
+        mapper.write(0xF800, 0x7F);
+
+        assert_eq!(mapper.read(0x4800), Some(0x11));
+
+        mapper.write(0xF800, 0x80);
+        mapper.write(0x6000, 0x33);
+
+        assert_eq!(mapper.read(0x6000), Some(0));
+
+        mapper.write(0xF800, 0x40);
+        mapper.write(0x6000, 0x33);
+
+        assert_eq!(mapper.read(0x6000), Some(0x33));
+
+        let battery = mapper.battery_ram().unwrap();
+
+        assert_eq!(battery.len(), 0x2000 + 128);
+        assert_eq!((battery[0x2000], battery[0x207F]), (0x22, 0x11));
+    }
+}
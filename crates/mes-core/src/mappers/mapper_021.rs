@@ -0,0 +1,313 @@
+// https://www.nesdev.org/wiki/VRC2_and_VRC4
+
+use super::{vrc_irq::VrcIrq, Mapper};
+use crate::{
+    cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
+    error::Error,
+    utils::{BitFlag, Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+/// CPU address lines connected to the chip register select pins, boards without a known
+/// submapper have both candidate lines OR'd together and run as a VRC4, which VRC2 games
+/// are compatible with.
+#[derive(Debug, Clone, Copy)]
+struct Wiring {
+    a0: u16,
+    a1: u16,
+    vrc2: bool,
+}
+
+impl Wiring {
+    // https://www.nesdev.org/wiki/NES_2.0_submappers#021,_022,_023,_025:_Konami_VRC2/VRC4
+    fn new(mapper: u16, submapper: u8) -> Self {
+        let (a0, a1, vrc2) = match (mapper, submapper) {
+            (21, 1) => (0x02, 0x04, false),               // VRC4a
+            (21, 2) => (0x40, 0x80, false),               // VRC4c
+            (21, _) => (0x02 | 0x40, 0x04 | 0x80, false), // VRC4a or VRC4c
+            (22, _) => (0x02, 0x01, true),                // VRC2a
+            (23, 1) => (0x01, 0x02, false),               // VRC4f
+            (23, 2) => (0x04, 0x08, false),               // VRC4e
+            (23, 3) => (0x01, 0x02, true),                // VRC2b
+            (23, _) => (0x01 | 0x04, 0x02 | 0x08, false), // VRC2b, VRC4f or VRC4e
+            (25, 1) => (0x02, 0x01, false),               // VRC4b
+            (25, 2) => (0x08, 0x04, false),               // VRC4d
+            (25, 3) => (0x02, 0x01, true),                // VRC2c
+            _ => (0x02 | 0x08, 0x01 | 0x04, false),       // VRC2c, VRC4b or VRC4d
+        };
+
+        Self { a0, a1, vrc2 }
+    }
+
+    fn translate(&self, address: u16) -> u16 {
+        let a0 = (address & self.a0 != 0) as u16;
+        let a1 = (address & self.a1 != 0) as u16;
+        (address & 0xF000) | (a1 << 1) | a0
+    }
+}
+
+#[derive(Debug)]
+pub struct Vrc4 {
+    cartridge: Cartridge,
+    wiring: Wiring,
+    prg_banks: [u8; 2],
+    prg_swap: bool,
+    chr_banks: [u16; 8],
+    mirroring: Mirroring,
+    irq: VrcIrq,
+}
+
+impl Vrc4 {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let header = &cartridge.header;
+        let wiring = Wiring::new(header.mapper, header.submapper);
+        let mirroring = header.mirroring;
+
+        Self {
+            cartridge,
+            wiring,
+            prg_banks: [0; 2],
+            prg_swap: false,
+            chr_banks: [0; 8],
+            mirroring,
+            irq: VrcIrq::default(),
+        }
+    }
+
+    fn prg_page(&self, address: u16) -> PrgPage {
+        let count = (self.cartridge.header.prg_rom_pages as usize * 2).max(2);
+        let second_last = count - 2;
+        let bank = match (address, self.prg_swap) {
+            (0x8000..=0x9FFF, false) | (0xC000..=0xDFFF, true) => self.prg_banks[0] as usize,
+            (0x8000..=0x9FFF, true) | (0xC000..=0xDFFF, false) => second_last,
+            (0xA000..=0xBFFF, _) => self.prg_banks[1] as usize,
+            _ => second_last + 1,
+        };
+
+        PrgPage::Index8(bank % count)
+    }
+
+    fn chr_page(&self, address: u16) -> ChrPage {
+        let bank = self.chr_banks[address as usize >> 10];
+        let bank = if self.cartridge.header.mapper == 22 {
+            bank >> 1 // VRC2a ignores the lowest bank bit
+        } else {
+            bank
+        };
+        let count = match self.cartridge.header.chr_rom_pages {
+            0 => 8,
+            pages => pages as usize * 8,
+        };
+
+        ChrPage::Index1(bank as usize % count)
+    }
+
+    fn write_chr_bank(&mut self, address: u16, value: u8) {
+        let index = ((address - 0xB000) >> 11) as usize | ((address >> 1) & 1) as usize;
+        let bank = &mut self.chr_banks[index];
+
+        *bank = match address & 1 {
+            0 => (*bank & 0x1F0) | (value as u16 & 0x0F),
+            _ => (*bank & 0x00F) | ((value as u16 & 0x1F) << 4),
+        };
+    }
+
+    fn write_mirroring(&mut self, value: u8) {
+        self.mirroring = match (value & 0b11, self.wiring.vrc2) {
+            (0, _) | (2, true) => Mirroring::Vertical,
+            (1, _) | (3, true) => Mirroring::Horizontal,
+            (2, _) => Mirroring::OneScreenLower,
+            _ => Mirroring::OneScreenUpper,
+        };
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        let vrc4 = !self.wiring.vrc2;
+
+        match self.wiring.translate(address) {
+            0x8000..=0x8003 => self.prg_banks[0] = value & 0x1F,
+            0x9000..=0x9001 => self.write_mirroring(value),
+            0x9002..=0x9003 if vrc4 => self.prg_swap = value.contains(1),
+            0x9002..=0x9003 => self.write_mirroring(value),
+            0xA000..=0xA003 => self.prg_banks[1] = value & 0x1F,
+            address @ 0xB000..=0xEFFF => self.write_chr_bank(address, value),
+            0xF000 if vrc4 => self.irq.write_latch_low(value),
+            0xF001 if vrc4 => self.irq.write_latch_high(value),
+            0xF002 if vrc4 => self.irq.write_control(value),
+            0xF003 if vrc4 => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+}
+
+impl Mapper for Vrc4 {
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
+            0x0000..=0x1FFF => self.cartridge.read_chr(address, self.chr_page(address)),
+            0x6000..=0x7FFF => self.cartridge.read_prg_ram(address),
+            0x8000..=0xFFFF => self.cartridge.read_prg_rom(address, self.prg_page(address)),
+            _ => return None,
+        })
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                let page = self.chr_page(address);
+                self.cartridge.write_chr(address, value, page)
+            }
+            0x6000..=0x7FFF => self.cartridge.write_prg_ram(address, value),
+            0x8000..=0xFFFF => self.write_register(address, value),
+            _ => {}
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.irq()
+    }
+}
+
+impl Clock for Vrc4 {
+    fn tick(&mut self) {
+        self.irq.tick();
+    }
+}
+
+impl Reset for Vrc4 {
+    fn reset(&mut self) {
+        self.prg_banks = [0; 2];
+        self.prg_swap = false;
+        self.chr_banks = [0; 8];
+        self.mirroring = self.cartridge.header.mirroring;
+        self.irq = VrcIrq::default();
+    }
+}
+
+impl Snapshot for Vrc4 {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_bytes(&self.prg_banks);
+        state.write_bool(self.prg_swap);
+
+        for bank in self.chr_banks {
+            state.write_u16(bank);
+        }
+
+        self.mirroring.save(state);
+        self.irq.save(state);
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        state.read_bytes(&mut self.prg_banks)?;
+        self.prg_swap = state.read_bool()?;
+
+        for bank in &mut self.chr_banks {
+            *bank = state.read_u16()?;
+        }
+
+        self.mirroring.load(state)?;
+        self.irq.load(state)?;
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Vrc4;
+    use crate::{
+        cartridge::{Cartridge, Mirroring},
+        mappers::Mapper,
+        utils::{test::banked_cartridge, Clock},
+    };
+
+    fn cartridge(mapper: u16, submapper: u8) -> Cartridge {
+        let mut cartridge = banked_cartridge(mapper, 8, 32);
+        cartridge.header.submapper = submapper;
+        cartridge
+    }
+
+    #[test]
+    fn test_vrc4e_wiring() {
+        let mut mapper = Vrc4::new(cartridge(23, 2));
+
+        mapper.write(0x8000, 3);
+        mapper.write(0xA000, 4);
+        mapper.write(0x9008, 0b10); // swap mode at $9002
+        mapper.write(0xC000, 0x0A); // bank 2 low at $C000
+        mapper.write(0xC00C, 0x01); // bank 3 high at $C003
+        mapper.write(0xC008, 0x0F); // bank 3 low at $C002
+
+        assert_eq!(mapper.read(0x8000), Some(14));
+        assert_eq!(mapper.read(0xA000), Some(4));
+        assert_eq!(mapper.read(0xC000), Some(3));
+        assert_eq!(mapper.read(0xE000), Some(15));
+        assert_eq!(mapper.read(0x0800), Some(0x0A));
+        assert_eq!(mapper.read(0x0C00), Some(0x1F));
+    }
+
+    #[test]
+    fn test_vrc2a_chr_and_mirroring() {
+        let mut mapper = Vrc4::new(cartridge(22, 0));
+
+        mapper.write(0xB000, 0x08);
+        mapper.write(0x9000, 0b11);
+
+        assert_eq!(mapper.read(0x0000), Some(0x04));
+        assert_eq!(mapper.get_mirroring(), Mirroring::Horizontal);
+
+        mapper.write(0xF000, 0xFF);
+        mapper.write(0xF002, 0b110);
+        mapper.tick();
+
+        assert!(!mapper.irq());
+    }
+
+    #[test]
+    fn test_vrc4_unknown_submapper() {
+        let mut mapper = Vrc4::new(cartridge(21, 0));
+
+        mapper.write(0x9000, 0b10);
+
+        assert_eq!(mapper.get_mirroring(), Mirroring::OneScreenLower);
+
+        mapper.write(0xF000, 0x0F);
+        mapper.write(0xF040, 0x0F); // $F001 on VRC4c
+        mapper.write(0xF004, 0b110); // $F002 on VRC4a
+        mapper.tick();
+
+        assert!(mapper.irq());
+    }
+
+    #[test]
+    fn test_vrc2b_unknown_submapper() {
+        let mut mapper = Vrc4::new(cartridge(23, 0));
+
+        mapper.write(0x8000, 3);
+        mapper.write(0xA000, 4);
+        mapper.write(0xB000, 0x0A);
+        mapper.write(0xB001, 0x01); // bank 0 high at $B001 on VRC2b
+        mapper.write(0xB002, 0x05); // bank 1 low at $B002 on VRC2b
+        mapper.write(0x9000, 1);
+
+        assert_eq!(mapper.read(0x8000), Some(3));
+        assert_eq!(mapper.read(0xA000), Some(4));
+        assert_eq!(mapper.read(0x0000), Some(0x1A));
+        assert_eq!(mapper.read(0x0400), Some(0x05));
+        assert_eq!(mapper.get_mirroring(), Mirroring::Horizontal);
+
+        mapper.write(0x9000, 0);
+
+        assert_eq!(mapper.get_mirroring(), Mirroring::Vertical);
+    }
+}
@@ -0,0 +1,244 @@
+// https://www.nesdev.org/wiki/VRC6
+
+use super::{vrc6_audio::Vrc6Audio, vrc_irq::VrcIrq, Mapper};
+use crate::{
+    apu::ExpansionAudio,
+    cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
+    error::Error,
+    utils::{BitFlag, Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+#[derive(Debug)]
+pub struct Vrc6 {
+    cartridge: Cartridge,
+    prg_banks: [u8; 2],
+    chr_banks: [u8; 8],
+    banking_mode: u8,
+    irq: VrcIrq,
+    audio: Vrc6Audio,
+}
+
+impl Vrc6 {
+    pub fn new(cartridge: Cartridge) -> Self {
+        Self {
+            cartridge,
+            prg_banks: [0; 2],
+            chr_banks: [0; 8],
+            banking_mode: 0,
+            irq: VrcIrq::default(),
+            audio: Vrc6Audio::default(),
+        }
+    }
+
+    /// Mapper 26 (VRC6b) has A0 and A1 swapped.
+    fn translate(&self, address: u16) -> u16 {
+        match self.cartridge.header.mapper {
+            26 => (address & 0xF000) | (address & 1) << 1 | (address >> 1) & 1,
+            _ => address & 0xF003,
+        }
+    }
+
+    fn prg_page(&self, address: u16) -> PrgPage {
+        let pages = (self.cartridge.header.prg_rom_pages as usize).max(1);
+
+        match address {
+            0x8000..=0xBFFF => PrgPage::Index16(self.prg_banks[0] as usize % pages),
+            0xC000..=0xDFFF => PrgPage::Index8(self.prg_banks[1] as usize % (pages * 2)),
+            _ => PrgPage::Index8(pages * 2 - 1),
+        }
+    }
+
+    fn chr_page(&self, address: u16) -> ChrPage {
+        let slot = address as usize >> 10;
+        let a10 = slot as u8 & 1;
+        let bank = match (self.banking_mode & 0b11, slot) {
+            (0, _) => self.chr_banks[slot],
+            (1, _) => (self.chr_banks[slot >> 1] & 0xFE) | a10,
+            (_, 0..=3) => self.chr_banks[slot],
+            (_, _) => (self.chr_banks[4 + ((slot - 4) >> 1)] & 0xFE) | a10,
+        };
+        let count = match self.cartridge.header.chr_rom_pages {
+            0 => 8,
+            pages => pages as usize * 8,
+        };
+
+        ChrPage::Index1(bank as usize % count)
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.banking_mode.contains(7)
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        match self.translate(address) {
+            0x8000..=0x8003 => self.prg_banks[0] = value & 0x0F,
+            address @ (0x9000..=0x9003 | 0xA000..=0xA002 | 0xB000..=0xB002) => {
+                self.audio.write_register(address, value)
+            }
+            0xB003 => self.banking_mode = value,
+            0xC000..=0xC003 => self.prg_banks[1] = value & 0x1F,
+            address @ 0xD000..=0xE003 => {
+                let index = ((address - 0xD000) >> 10 | address & 0b11) as usize;
+                self.chr_banks[index] = value;
+            }
+            0xF000 => self.irq.write_latch(value),
+            0xF001 => self.irq.write_control(value),
+            0xF002 => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+}
+
+impl Mapper for Vrc6 {
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
+            0x0000..=0x1FFF => self.cartridge.read_chr(address, self.chr_page(address)),
+            0x6000..=0x7FFF if self.prg_ram_enabled() => self.cartridge.read_prg_ram(address),
+            0x8000..=0xFFFF => self.cartridge.read_prg_rom(address, self.prg_page(address)),
+            _ => return None,
+        })
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                let page = self.chr_page(address);
+                self.cartridge.write_chr(address, value, page)
+            }
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                self.cartridge.write_prg_ram(address, value)
+            }
+            0x8000..=0xFFFF => self.write_register(address, value),
+            _ => {}
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirroring {
+        match self.banking_mode.get_range(2..4) {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::OneScreenLower,
+            _ => Mirroring::OneScreenUpper,
+        }
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.irq()
+    }
+
+    fn expansion_audio(&self) -> Option<&dyn ExpansionAudio> {
+        Some(&self.audio)
+    }
+}
+
+impl Clock for Vrc6 {
+    fn tick(&mut self) {
+        self.irq.tick();
+        self.audio.tick();
+    }
+}
+
+impl Reset for Vrc6 {
+    fn reset(&mut self) {
+        self.prg_banks = [0; 2];
+        self.chr_banks = [0; 8];
+        self.banking_mode = 0;
+        self.irq = VrcIrq::default();
+        self.audio = Vrc6Audio::default();
+    }
+}
+
+impl Snapshot for Vrc6 {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_bytes(&self.prg_banks);
+        state.write_bytes(&self.chr_banks);
+        state.write_u8(self.banking_mode);
+        self.irq.save(state);
+        self.audio.save(state);
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        state.read_bytes(&mut self.prg_banks)?;
+        state.read_bytes(&mut self.chr_banks)?;
+        self.banking_mode = state.read_u8()?;
+        self.irq.load(state)?;
+        self.audio.load(state)?;
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Vrc6;
+    use crate::{
+        cartridge::Mirroring,
+        mappers::Mapper,
+        utils::{test::banked_cartridge, Clock},
+    };
+
+    fn vrc6(mapper: u16) -> Vrc6 {
+        Vrc6::new(banked_cartridge(mapper, 8, 32))
+    }
+
+    #[test]
+    fn test_vrc6_large_prg() {
+        let mut mapper = Vrc6::new(banked_cartridge(24, 128, 0));
+
+        mapper.write(0x8000, 0x0F);
+        mapper.write(0xC000, 0x1F);
+
+        assert_eq!(mapper.read(0x8000), Some(30));
+        assert_eq!(mapper.read(0xA000), Some(31));
+        assert_eq!(mapper.read(0xC000), Some(31));
+        assert_eq!(mapper.read(0xE000), Some(0xFF));
+    }
+
+    #[test]
+    fn test_vrc6_banking() {
+        for (mapper, r1, r3) in [(24, 0xD001, 0xD003), (26, 0xD002, 0xD003)] {
+            let mut mapper = vrc6(mapper);
+
+            mapper.write(0x8000, 2);
+            mapper.write(0xC000, 9);
+            mapper.write(r1, 0x11);
+            mapper.write(r3, 0x33);
+            mapper.write(0xB003, 0b1000_0100);
+
+            assert_eq!(mapper.read(0x8000), Some(4));
+            assert_eq!(mapper.read(0xA000), Some(5));
+            assert_eq!(mapper.read(0xC000), Some(9));
+            assert_eq!(mapper.read(0xE000), Some(15));
+            assert_eq!(mapper.read(0x0400), Some(0x11));
+            assert_eq!(mapper.read(0x0C00), Some(0x33));
+            assert_eq!(mapper.get_mirroring(), Mirroring::Horizontal);
+        }
+    }
+
+    #[test]
+    fn test_vrc6_irq() {
+        let mut mapper = vrc6(26);
+
+        mapper.write(0xF000, 0xFE);
+        mapper.write(0xF002, 0b110); // $F001 on VRC6b
+        mapper.tick();
+
+        assert!(!mapper.irq());
+
+        mapper.tick();
+
+        assert!(mapper.irq());
+
+        mapper.write(0xF001, 0); // $F002 on VRC6b
+
+        assert!(!mapper.irq());
+    }
+}
@@ -0,0 +1,319 @@
+// https://www.nesdev.org/wiki/UNROM_512
+
+use super::{bus_conflict, Mapper};
+use crate::{
+    cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
+    error::Error,
+    utils::{BitFlag, Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+const CHR_RAM_SIZE: usize = 0x8000;
+const SECTOR_SIZE: usize = 0x1000;
+
+// SST39SF040 software product identification
+const FLASH_ID: [u8; 2] = [0xBF, 0xB7];
+
+/// Position in the SST39SF040 command sequences.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FlashState {
+    Idle,
+    Unlocked,
+    Command,
+    Program,
+    EraseSetup,
+    EraseUnlocked,
+    EraseCommand,
+}
+
+#[derive(Debug)]
+pub struct Unrom512 {
+    cartridge: Cartridge,
+    bank_register: u8,
+    flash_state: FlashState,
+    identify: bool,
+    flash_dirty: bool,
+}
+
+impl Unrom512 {
+    pub fn new(mut cartridge: Cartridge) -> Self {
+        // iNES headers can only describe 8 KB of CHR RAM
+        if cartridge.header.chr_rom_pages == 0 && cartridge.chr_ram.len() < CHR_RAM_SIZE {
+            cartridge.chr_ram.resize(CHR_RAM_SIZE, 0);
+        }
+
+        Self {
+            cartridge,
+            bank_register: 0,
+            flash_state: FlashState::Idle,
+            identify: false,
+            flash_dirty: false,
+        }
+    }
+
+    /// Self-flashable boards are marked with the battery flag and have no bus conflicts.
+    fn is_flashable(&self) -> bool {
+        self.cartridge.header.battery
+    }
+
+    fn prg_bank(&self) -> usize {
+        let count = self.cartridge.header.prg_rom_pages.max(1) as usize;
+        (self.bank_register & 0x1F) as usize % count
+    }
+
+    fn chr_page(&self) -> ChrPage {
+        let count = (self.cartridge.chr_ram.len() / 0x2000).max(1);
+        ChrPage::Index8(self.bank_register.get_range(5..7) as usize % count)
+    }
+
+    fn write_flash(&mut self, address: u16, value: u8) {
+        let offset = self.prg_bank() * 0x4000 + (address as usize & 0x3FFF);
+        let command_address = offset & 0x7FFF;
+
+        self.flash_state = match (self.flash_state, command_address, value) {
+            (FlashState::Program, _, _) => {
+                self.program_flash(offset, value);
+                FlashState::Idle
+            }
+            (_, _, 0xF0) => {
+                self.identify = false;
+                FlashState::Idle
+            }
+            (FlashState::Idle, 0x5555, 0xAA) => FlashState::Unlocked,
+            (FlashState::Unlocked, 0x2AAA, 0x55) => FlashState::Command,
+            (FlashState::Command, 0x5555, 0xA0) => FlashState::Program,
+            (FlashState::Command, 0x5555, 0x80) => FlashState::EraseSetup,
+            (FlashState::Command, 0x5555, 0x90) => {
+                self.identify = true;
+                FlashState::Idle
+            }
+            (FlashState::EraseSetup, 0x5555, 0xAA) => FlashState::EraseUnlocked,
+            (FlashState::EraseUnlocked, 0x2AAA, 0x55) => FlashState::EraseCommand,
+            (FlashState::EraseCommand, _, 0x30) => {
+                let start = offset & !(SECTOR_SIZE - 1);
+                self.erase_flash(start..start + SECTOR_SIZE);
+                FlashState::Idle
+            }
+            (FlashState::EraseCommand, 0x5555, 0x10) => {
+                self.erase_flash(0..self.cartridge.prg_rom.len());
+                FlashState::Idle
+            }
+            _ => FlashState::Idle,
+        };
+    }
+
+    fn program_flash(&mut self, offset: usize, value: u8) {
+        if let Some(byte) = self.cartridge.prg_rom.get_mut(offset) {
+            // programming can only clear bits
+            *byte &= value;
+            self.flash_dirty = true;
+        }
+    }
+
+    fn erase_flash(&mut self, range: std::ops::Range<usize>) {
+        if let Some(bytes) = self.cartridge.prg_rom.get_mut(range) {
+            bytes.fill(0xFF);
+            self.flash_dirty = true;
+        }
+    }
+}
+
+impl Mapper for Unrom512 {
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
+            0x0000..=0x1FFF => self.cartridge.read_chr(address, self.chr_page()),
+            0x8000..=0xFFFF if self.identify => FLASH_ID[address as usize & 1],
+            0x8000..=0xBFFF => self
+                .cartridge
+                .read_prg_rom(address, PrgPage::Index16(self.prg_bank())),
+            0xC000..=0xFFFF => self.cartridge.read_prg_rom(address, PrgPage::Last16),
+            _ => return None,
+        })
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                let page = self.chr_page();
+                self.cartridge.write_chr(address, value, page)
+            }
+            0x8000..=0xBFFF if self.is_flashable() => self.write_flash(address, value),
+            0xC000..=0xFFFF if self.is_flashable() => self.bank_register = value,
+            0x8000..=0xFFFF => self.bank_register = bus_conflict(self, address, value),
+            _ => {}
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirroring {
+        match self.cartridge.header.mirroring {
+            // the four-screen bit alone selects switchable one-screen mirroring on this board
+            Mirroring::FourScreen if self.cartridge.header.vertical_mirroring => {
+                Mirroring::FourScreen
+            }
+            Mirroring::FourScreen if self.bank_register.contains(7) => Mirroring::OneScreenUpper,
+            Mirroring::FourScreen => Mirroring::OneScreenLower,
+            mirroring => mirroring,
+        }
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    fn battery_ram(&self) -> Option<&[u8]> {
+        self.is_flashable()
+            .then_some(self.cartridge.prg_rom.as_slice())
+    }
+
+    fn load_battery_ram(&mut self, bytes: &[u8]) {
+        if self.is_flashable() {
+            let length = bytes.len().min(self.cartridge.prg_rom.len());
+            self.cartridge.prg_rom[..length].copy_from_slice(&bytes[..length]);
+            self.flash_dirty = false;
+        }
+    }
+
+    fn take_battery_ram_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.flash_dirty)
+    }
+}
+
+impl Clock for Unrom512 {}
+
+impl Reset for Unrom512 {
+    fn reset(&mut self) {
+        self.bank_register = 0;
+        self.flash_state = FlashState::Idle;
+        self.identify = false;
+    }
+}
+
+impl Snapshot for Unrom512 {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.bank_register);
+        state.write_u8(self.flash_state as u8);
+        state.write_bool(self.identify);
+
+        if self.is_flashable() {
+            state.write_bytes(&self.cartridge.prg_rom);
+        }
+
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.bank_register = state.read_u8()?;
+        self.flash_state = match state.read_u8()? {
+            0 => FlashState::Idle,
+            1 => FlashState::Unlocked,
+            2 => FlashState::Command,
+            3 => FlashState::Program,
+            4 => FlashState::EraseSetup,
+            5 => FlashState::EraseUnlocked,
+            6 => FlashState::EraseCommand,
+            _ => return Err(Error::InvalidSaveState),
+        };
+        self.identify = state.read_bool()?;
+
+        if self.is_flashable() {
+            state.read_bytes(&mut self.cartridge.prg_rom)?;
+        }
+
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Unrom512;
+    use crate::{
+        cartridge::{Cartridge, Header, Mirroring},
+        mappers::Mapper,
+    };
+
+    fn unrom512(battery: bool, vertical_mirroring: bool) -> Unrom512 {
+        Unrom512::new(Cartridge {
+            header: Header {
+                prg_rom_pages: 32,
+                chr_rom_pages: 0,
+                mapper: 30,
+                mirroring: Mirroring::FourScreen,
+                vertical_mirroring,
+                battery,
+                ..Cartridge::default().header
+            },
+            prg_rom: (0..32).flat_map(|bank| vec![0xE0 | bank; 0x4000]).collect(),
+            chr_ram: vec![0; 0x2000],
+            ..Default::default()
+        })
+    }
+
+    fn flash_command(mapper: &mut Unrom512, command: u8) {
+        for (bank, address, value) in [(1, 0x9555, 0xAA), (0, 0xAAAA, 0x55), (1, 0x9555, command)] {
+            mapper.write(0xC000, bank);
+            mapper.write(address, value);
+        }
+    }
+
+    #[test]
+    fn test_unrom512_banking() {
+        let mut mapper = unrom512(false, false);
+
+        mapper.write(0xC000, 0xE3);
+        mapper.write(0x0000, 0x42);
+
+        assert_eq!(mapper.read(0x8000), Some(0xE3));
+        assert_eq!(mapper.read(0xC000), Some(0xFF));
+        assert_eq!(mapper.read(0x0000), Some(0x42));
+        assert_eq!(mapper.get_mirroring(), Mirroring::OneScreenUpper);
+
+        // bus conflict with 0xE3
+        mapper.write(0x8000, 0x1C);
+
+        assert_eq!(mapper.read(0x8000), Some(0xE0));
+        assert_eq!(mapper.read(0x0000), Some(0));
+        assert_eq!(mapper.get_mirroring(), Mirroring::OneScreenLower);
+    }
+
+    #[test]
+    fn test_unrom512_four_screen() {
+        let mut mapper = unrom512(false, true);
+
+        mapper.write(0xC000, 0x80);
+
+        assert_eq!(mapper.get_mirroring(), Mirroring::FourScreen);
+    }
+
+    #[test]
+    fn test_unrom512_flash() {
+        let mut mapper = unrom512(true, false);
+
+        mapper.write(0xC000, 2);
+        mapper.write(0x8000, 0x00);
+
+        assert_eq!(mapper.read(0x8000), Some(0xE2));
+        assert!(!mapper.take_battery_ram_dirty());
+
+        flash_command(&mut mapper, 0x80);
+        mapper.write(0xC000, 1);
+        mapper.write(0x9555, 0xAA);
+        mapper.write(0xC000, 0);
+        mapper.write(0xAAAA, 0x55);
+        mapper.write(0xC000, 2);
+        mapper.write(0x8000, 0x30);
+
+        assert_eq!(mapper.read(0x8000), Some(0xFF));
+        assert_eq!(mapper.read(0x9000), Some(0xE2));
+
+        flash_command(&mut mapper, 0xA0);
+        mapper.write(0xC000, 2);
+        mapper.write(0x8000, 0x5A);
+
+        assert_eq!(mapper.read(0x8000), Some(0x5A));
+        assert!(mapper.take_battery_ram_dirty());
+        assert_eq!(mapper.battery_ram().unwrap()[0x8000], 0x5A);
+    }
+}
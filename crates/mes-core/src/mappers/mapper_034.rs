@@ -0,0 +1,185 @@
+// https://www.nesdev.org/wiki/INES_Mapper_034
+
+use super::{bus_conflict, Mapper};
+use crate::{
+    cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
+    error::Error,
+    utils::{Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+/// BNROM and NINA-001, which share mapper 34.
+#[derive(Debug)]
+pub struct BnRom {
+    cartridge: Cartridge,
+    prg_bank: u8,
+    chr_banks: [u8; 2],
+    nina001: bool,
+}
+
+impl BnRom {
+    pub fn new(cartridge: Cartridge) -> Self {
+        // without a submapper, only NINA-001 has more than 8 KB of CHR ROM
+        let nina001 = match cartridge.header.submapper {
+            1 => true,
+            2 => false,
+            _ => cartridge.header.chr_rom_pages > 1,
+        };
+
+        Self {
+            cartridge,
+            prg_bank: 0,
+            chr_banks: [0, 1],
+            nina001,
+        }
+    }
+
+    fn prg_page(&self) -> PrgPage {
+        let count = (self.cartridge.header.prg_rom_pages as usize / 2).max(1);
+        PrgPage::Index32(self.prg_bank as usize % count)
+    }
+
+    fn chr_page(&self, address: u16) -> ChrPage {
+        match self.nina001 {
+            true => {
+                let count = (self.cartridge.header.chr_rom_pages as usize * 2).max(1);
+                ChrPage::Index4(self.chr_banks[address as usize >> 12] as usize % count)
+            }
+            false => ChrPage::Index8(0),
+        }
+    }
+}
+
+impl Mapper for BnRom {
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
+            0x0000..=0x1FFF => self.cartridge.read_chr(address, self.chr_page(address)),
+            0x6000..=0x7FFF => self.cartridge.read_prg_ram(address),
+            0x8000..=0xFFFF => self.cartridge.read_prg_rom(address, self.prg_page()),
+            _ => return None,
+        })
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                let page = self.chr_page(address);
+                self.cartridge.write_chr(address, value, page)
+            }
+            0x6000..=0x7FFF => {
+                // the NINA-001 registers overlay the last bytes of the PRG RAM
+                match address {
+                    0x7FFD if self.nina001 => self.prg_bank = value & 1,
+                    0x7FFE..=0x7FFF if self.nina001 => {
+                        self.chr_banks[address as usize - 0x7FFE] = value & 0x0F
+                    }
+                    _ => {}
+                }
+
+                self.cartridge.write_prg_ram(address, value)
+            }
+            0x8000..=0xFFFF if !self.nina001 => self.prg_bank = bus_conflict(self, address, value),
+            _ => {}
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirroring {
+        self.cartridge.header.mirroring
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+}
+
+impl Clock for BnRom {}
+
+impl Reset for BnRom {
+    fn reset(&mut self) {
+        self.prg_bank = 0;
+        self.chr_banks = [0, 1];
+    }
+}
+
+impl Snapshot for BnRom {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.prg_bank);
+        state.write_bytes(&self.chr_banks);
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.prg_bank = state.read_u8()?;
+        state.read_bytes(&mut self.chr_banks)?;
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BnRom;
+    use crate::{
+        cartridge::{Cartridge, Header},
+        mappers::Mapper,
+    };
+
+    fn cartridge(submapper: u8, chr_rom_pages: u16) -> Cartridge {
+        Cartridge {
+            header: Header {
+                prg_rom_pages: 8,
+                chr_rom_pages,
+                mapper: 34,
+                submapper,
+                ..Cartridge::default().header
+            },
+            prg_rom: (0..4)
+                .flat_map(|bank| vec![bank << 4 | 3; 0x8000])
+                .collect(),
+            chr_rom: (0..chr_rom_pages as u8 * 2)
+                .flat_map(|bank| vec![bank; 0x1000])
+                .collect(),
+            prg_ram: vec![0; 0x2000],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_bnrom_banking() {
+        let mut mapper = BnRom::new(cartridge(2, 1));
+
+        mapper.write(0x8000, 0x02);
+
+        assert_eq!(mapper.read(0x8000), Some(0x23));
+
+        mapper.write(0x8000, 0x01);
+
+        assert_eq!(mapper.read(0x8000), Some(0x13));
+
+        // bus conflict with 0x13
+        mapper.write(0x8000, 0x06);
+
+        assert_eq!(mapper.read(0x8000), Some(0x23));
+
+        mapper.write(0x7FFD, 0x01);
+
+        assert_eq!(mapper.read(0x8000), Some(0x23));
+    }
+
+    #[test]
+    fn test_nina001_banking() {
+        let mut mapper = BnRom::new(cartridge(0, 4));
+
+        mapper.write(0x7FFD, 0x01);
+        mapper.write(0x7FFE, 0x05);
+        mapper.write(0x7FFF, 0x02);
+        mapper.write(0x8000, 0x00);
+
+        assert_eq!(mapper.read(0x8000), Some(0x13));
+        assert_eq!(mapper.read(0x0000), Some(0x05));
+        assert_eq!(mapper.read(0x1000), Some(0x02));
+        assert_eq!(mapper.read(0x7FFE), Some(0x05));
+    }
+}
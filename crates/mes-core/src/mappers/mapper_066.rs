@@ -0,0 +1,136 @@
+// https://www.nesdev.org/wiki/GxROM
+
+use super::{bus_conflict, Mapper};
+use crate::{
+    cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
+    error::Error,
+    utils::{Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+#[derive(Debug)]
+pub struct GxRom {
+    cartridge: Cartridge,
+    prg_bank: u8,
+    chr_bank: u8,
+}
+
+impl GxRom {
+    pub fn new(cartridge: Cartridge) -> Self {
+        Self {
+            cartridge,
+            prg_bank: 0,
+            chr_bank: 0,
+        }
+    }
+
+    fn prg_page(&self) -> PrgPage {
+        let count = (self.cartridge.header.prg_rom_pages as usize / 2).max(1);
+        PrgPage::Index32(self.prg_bank as usize % count)
+    }
+
+    fn chr_page(&self) -> ChrPage {
+        let count = self.cartridge.header.chr_rom_pages.max(1) as usize;
+        ChrPage::Index8(self.chr_bank as usize % count)
+    }
+}
+
+impl Mapper for GxRom {
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
+            0x0000..=0x1FFF => self.cartridge.read_chr(address, self.chr_page()),
+            0x8000..=0xFFFF => self.cartridge.read_prg_rom(address, self.prg_page()),
+            _ => return None,
+        })
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                let page = self.chr_page();
+                self.cartridge.write_chr(address, value, page)
+            }
+            0x8000..=0xFFFF => {
+                let value = bus_conflict(self, address, value);
+
+                self.prg_bank = (value >> 4) & 0b11;
+                self.chr_bank = value & 0b11;
+            }
+            _ => {}
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirroring {
+        self.cartridge.header.mirroring
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+}
+
+impl Clock for GxRom {}
+
+impl Reset for GxRom {
+    fn reset(&mut self) {
+        self.prg_bank = 0;
+        self.chr_bank = 0;
+    }
+}
+
+impl Snapshot for GxRom {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.prg_bank);
+        state.write_u8(self.chr_bank);
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.prg_bank = state.read_u8()?;
+        self.chr_bank = state.read_u8()?;
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GxRom;
+    use crate::{
+        cartridge::{Cartridge, Header},
+        mappers::Mapper,
+    };
+
+    #[test]
+    fn test_gxrom_bus_conflict() {
+        let mut prg_rom = (0..4)
+            .flat_map(|bank| vec![bank; 0x8000])
+            .collect::<Vec<_>>();
+        prg_rom[0x7FF0] = 0x33;
+
+        let mut mapper = GxRom::new(Cartridge {
+            header: Header {
+                prg_rom_pages: 8,
+                chr_rom_pages: 4,
+                mapper: 66,
+                ..Cartridge::default().header
+            },
+            prg_rom,
+            chr_rom: (0..4).flat_map(|bank| vec![bank; 0x2000]).collect(),
+            ..Default::default()
+        });
+
+        mapper.write(0xFFF0, 0x12);
+
+        assert_eq!(mapper.read(0x8000), Some(1));
+        assert_eq!(mapper.read(0x0000), Some(2));
+
+        // bank 1 has 0x01 everywhere, so only bit 0 survives the conflict
+        mapper.write(0x8000, 0x33);
+
+        assert_eq!(mapper.read(0x8000), Some(0));
+        assert_eq!(mapper.read(0x0000), Some(1));
+    }
+}
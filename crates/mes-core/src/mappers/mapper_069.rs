@@ -0,0 +1,260 @@
+// https://www.nesdev.org/wiki/Sunsoft_FME-7
+
+use super::{sunsoft_5b::Sunsoft5B, Mapper};
+use crate::{
+    apu::ExpansionAudio,
+    cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
+    error::Error,
+    utils::{BitFlag, Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+#[derive(Debug)]
+pub struct Fme7 {
+    cartridge: Cartridge,
+    command: u8,
+    chr_banks: [u8; 8],
+    prg_banks: [u8; 4],
+    mirroring: Mirroring,
+    irq_enabled: bool,
+    counter_enabled: bool,
+    irq_counter: u16,
+    interrupt: bool,
+    audio: Sunsoft5B,
+}
+
+impl Fme7 {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let mirroring = cartridge.header.mirroring;
+
+        Self {
+            cartridge,
+            command: 0,
+            chr_banks: [0; 8],
+            prg_banks: [0; 4],
+            mirroring,
+            irq_enabled: false,
+            counter_enabled: false,
+            irq_counter: 0,
+            interrupt: false,
+            audio: Sunsoft5B::default(),
+        }
+    }
+
+    fn prg_page(&self, address: u16) -> PrgPage {
+        let count = (self.cartridge.header.prg_rom_pages as usize * 2).max(1);
+        let bank = match address {
+            0x6000..=0x7FFF => (self.prg_banks[0] & 0x3F) as usize,
+            0x8000..=0x9FFF => (self.prg_banks[1] & 0x3F) as usize,
+            0xA000..=0xBFFF => (self.prg_banks[2] & 0x3F) as usize,
+            0xC000..=0xDFFF => (self.prg_banks[3] & 0x3F) as usize,
+            _ => count - 1,
+        };
+
+        PrgPage::Index8(bank % count)
+    }
+
+    fn chr_page(&self, address: u16) -> ChrPage {
+        let count = match self.cartridge.header.chr_rom_pages {
+            0 => 8,
+            pages => pages as usize * 8,
+        };
+        let bank = self.chr_banks[address as usize >> 10];
+
+        ChrPage::Index1(bank as usize % count)
+    }
+
+    fn prg_ram_selected(&self) -> bool {
+        self.prg_banks[0].contains(6)
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.prg_ram_selected() && self.prg_banks[0].contains(7)
+    }
+
+    fn write_parameter(&mut self, value: u8) {
+        match self.command {
+            0x0..=0x7 => self.chr_banks[self.command as usize] = value,
+            0x8..=0xB => self.prg_banks[self.command as usize - 0x8] = value,
+            0xC => {
+                self.mirroring = match value & 0b11 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::OneScreenLower,
+                    _ => Mirroring::OneScreenUpper,
+                }
+            }
+            0xD => {
+                self.irq_enabled = value.contains(0);
+                self.counter_enabled = value.contains(7);
+                self.interrupt = false;
+            }
+            0xE => self.irq_counter = (self.irq_counter & 0xFF00) | value as u16,
+            _ => self.irq_counter = (self.irq_counter & 0x00FF) | (value as u16) << 8,
+        }
+    }
+}
+
+impl Mapper for Fme7 {
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
+            0x0000..=0x1FFF => self.cartridge.read_chr(address, self.chr_page(address)),
+            0x6000..=0x7FFF if self.prg_ram_enabled() => self.cartridge.read_prg_ram(address),
+            0x6000..=0x7FFF if self.prg_ram_selected() => return None,
+            0x6000..=0xFFFF => self.cartridge.read_prg_rom(address, self.prg_page(address)),
+            _ => return None,
+        })
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                let page = self.chr_page(address);
+                self.cartridge.write_chr(address, value, page)
+            }
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                self.cartridge.write_prg_ram(address, value)
+            }
+            0x8000..=0x9FFF => self.command = value & 0x0F,
+            0xA000..=0xBFFF => self.write_parameter(value),
+            0xC000..=0xDFFF => self.audio.write_select(value),
+            0xE000..=0xFFFF => self.audio.write_data(value),
+            _ => {}
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    fn irq(&self) -> bool {
+        self.interrupt
+    }
+
+    fn expansion_audio(&self) -> Option<&dyn ExpansionAudio> {
+        Some(&self.audio)
+    }
+}
+
+impl Clock for Fme7 {
+    fn tick(&mut self) {
+        if self.counter_enabled {
+            self.irq_counter = self.irq_counter.wrapping_sub(1);
+
+            if self.irq_counter == 0xFFFF && self.irq_enabled {
+                self.interrupt = true;
+            }
+        }
+
+        self.audio.tick();
+    }
+}
+
+impl Reset for Fme7 {
+    fn reset(&mut self) {
+        self.command = 0;
+        self.chr_banks = [0; 8];
+        self.prg_banks = [0; 4];
+        self.mirroring = self.cartridge.header.mirroring;
+        self.irq_enabled = false;
+        self.counter_enabled = false;
+        self.irq_counter = 0;
+        self.interrupt = false;
+        self.audio = Sunsoft5B::default();
+    }
+}
+
+impl Snapshot for Fme7 {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.command);
+        state.write_bytes(&self.chr_banks);
+        state.write_bytes(&self.prg_banks);
+        self.mirroring.save(state);
+        state.write_bool(self.irq_enabled);
+        state.write_bool(self.counter_enabled);
+        state.write_u16(self.irq_counter);
+        state.write_bool(self.interrupt);
+        self.audio.save(state);
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.command = state.read_u8()? & 0x0F;
+        state.read_bytes(&mut self.chr_banks)?;
+        state.read_bytes(&mut self.prg_banks)?;
+        self.mirroring.load(state)?;
+        self.irq_enabled = state.read_bool()?;
+        self.counter_enabled = state.read_bool()?;
+        self.irq_counter = state.read_u16()?;
+        self.interrupt = state.read_bool()?;
+        self.audio.load(state)?;
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fme7;
+    use crate::{
+        mappers::Mapper,
+        utils::{test::banked_cartridge, Clock},
+    };
+
+    fn fme7() -> Fme7 {
+        Fme7::new(banked_cartridge(69, 8, 32))
+    }
+
+    fn command(mapper: &mut Fme7, command: u8, value: u8) {
+        mapper.write(0x8000, command);
+        mapper.write(0xA000, value);
+    }
+
+    #[test]
+    fn test_fme7_banking() {
+        let mut mapper = fme7();
+
+        command(&mut mapper, 0x5, 0x21);
+        command(&mut mapper, 0x9, 0x03);
+        command(&mut mapper, 0x8, 0x07);
+
+        assert_eq!(mapper.read(0x1400), Some(0x21));
+        assert_eq!(mapper.read(0x8000), Some(3));
+        assert_eq!(mapper.read(0x6000), Some(7));
+        assert_eq!(mapper.read(0xE000), Some(15));
+
+        mapper.write(0x6000, 0x42);
+        command(&mut mapper, 0x8, 0xC0);
+        mapper.write(0x6000, 0x42);
+
+        assert_eq!(mapper.read(0x6000), Some(0x42));
+    }
+
+    #[test]
+    fn test_fme7_irq_counter() {
+        let mut mapper = fme7();
+
+        command(&mut mapper, 0xE, 0x02);
+        command(&mut mapper, 0xF, 0x00);
+        command(&mut mapper, 0xD, 0x81);
+
+        mapper.tick();
+        mapper.tick();
+
+        assert!(!mapper.irq());
+
+        mapper.tick();
+
+        assert!(mapper.irq());
+
+        command(&mut mapper, 0xD, 0x81);
+
+        assert!(!mapper.irq());
+    }
+}
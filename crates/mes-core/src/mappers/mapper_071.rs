@@ -0,0 +1,156 @@
+// https://www.nesdev.org/wiki/INES_Mapper_071
+
+use super::Mapper;
+use crate::{
+    cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
+    error::Error,
+    utils::{BitFlag, Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+/// Camerica/Codemasters BF909x boards, submapper 1 is the BF9097 used by Fire Hawk.
+#[derive(Debug)]
+pub struct Camerica {
+    cartridge: Cartridge,
+    prg_bank: u8,
+    mirroring: Mirroring,
+}
+
+impl Camerica {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let mirroring = cartridge.header.mirroring;
+
+        Self {
+            cartridge,
+            prg_bank: 0,
+            mirroring,
+        }
+    }
+
+    fn prg_bank_count(&self) -> usize {
+        self.cartridge.header.prg_rom_pages.max(1) as usize
+    }
+
+    /// Without a submapper, Fire Hawk is recognized by its writes to $9000-$9FFF.
+    fn is_mirroring_register(&self, address: u16) -> bool {
+        match self.cartridge.header.submapper {
+            1 => (0x8000..=0x9FFF).contains(&address),
+            _ => (0x9000..=0x9FFF).contains(&address),
+        }
+    }
+}
+
+impl Mapper for Camerica {
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
+            0x0000..=0x1FFF => self.cartridge.read_chr(address, ChrPage::Index8(0)),
+            0x8000..=0xBFFF => self.cartridge.read_prg_rom(
+                address,
+                PrgPage::Index16(self.prg_bank as usize % self.prg_bank_count()),
+            ),
+            0xC000..=0xFFFF => self.cartridge.read_prg_rom(address, PrgPage::Last16),
+            _ => return None,
+        })
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.cartridge.write_chr(address, value, ChrPage::Index8(0)),
+            0x8000..=0xBFFF if self.is_mirroring_register(address) => {
+                self.mirroring = match value.contains(4) {
+                    true => Mirroring::OneScreenUpper,
+                    false => Mirroring::OneScreenLower,
+                };
+            }
+            0xC000..=0xFFFF => self.prg_bank = value & 0x0F,
+            _ => {}
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+}
+
+impl Clock for Camerica {}
+
+impl Reset for Camerica {
+    fn reset(&mut self) {
+        self.prg_bank = 0;
+        self.mirroring = self.cartridge.header.mirroring;
+    }
+}
+
+impl Snapshot for Camerica {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.prg_bank);
+        self.mirroring.save(state);
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.prg_bank = state.read_u8()?;
+        self.mirroring.load(state)?;
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Camerica;
+    use crate::{
+        cartridge::{Cartridge, Header, Mirroring},
+        mappers::Mapper,
+    };
+
+    fn camerica(submapper: u8) -> Camerica {
+        Camerica::new(Cartridge {
+            header: Header {
+                prg_rom_pages: 8,
+                chr_rom_pages: 0,
+                mapper: 71,
+                submapper,
+                ..Cartridge::default().header
+            },
+            prg_rom: (0..8).flat_map(|bank| vec![bank; 0x4000]).collect(),
+            chr_ram: vec![0; 0x2000],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_camerica_banking() {
+        let mut mapper = camerica(0);
+
+        mapper.write(0xC000, 0x13);
+        mapper.write(0x8000, 0x10);
+
+        assert_eq!(mapper.read(0x8000), Some(3));
+        assert_eq!(mapper.read(0xC000), Some(7));
+        assert_eq!(mapper.get_mirroring(), Mirroring::Vertical);
+
+        mapper.write(0x9000, 0x10);
+
+        assert_eq!(mapper.get_mirroring(), Mirroring::OneScreenUpper);
+    }
+
+    #[test]
+    fn test_fire_hawk_mirroring() {
+        let mut mapper = camerica(1);
+
+        mapper.write(0x8000, 0x10);
+
+        assert_eq!(mapper.get_mirroring(), Mirroring::OneScreenUpper);
+
+        mapper.write(0x8000, 0x00);
+
+        assert_eq!(mapper.get_mirroring(), Mirroring::OneScreenLower);
+    }
+}
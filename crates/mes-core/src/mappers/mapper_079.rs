@@ -0,0 +1,137 @@
+// https://www.nesdev.org/wiki/NINA-003-006
+
+use super::Mapper;
+use crate::{
+    cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
+    error::Error,
+    utils::{BitFlag, Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+#[derive(Debug)]
+pub struct Nina003 {
+    cartridge: Cartridge,
+    prg_bank: u8,
+    chr_bank: u8,
+}
+
+impl Nina003 {
+    pub fn new(cartridge: Cartridge) -> Self {
+        Self {
+            cartridge,
+            prg_bank: 0,
+            chr_bank: 0,
+        }
+    }
+
+    fn prg_page(&self) -> PrgPage {
+        let count = (self.cartridge.header.prg_rom_pages as usize / 2).max(1);
+        PrgPage::Index32(self.prg_bank as usize % count)
+    }
+
+    fn chr_page(&self) -> ChrPage {
+        let count = self.cartridge.header.chr_rom_pages.max(1) as usize;
+        ChrPage::Index8(self.chr_bank as usize % count)
+    }
+}
+
+impl Mapper for Nina003 {
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
+            0x0000..=0x1FFF => self.cartridge.read_chr(address, self.chr_page()),
+            0x8000..=0xFFFF => self.cartridge.read_prg_rom(address, self.prg_page()),
+            _ => return None,
+        })
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                let page = self.chr_page();
+                self.cartridge.write_chr(address, value, page)
+            }
+            // the latch is selected by A14 and A8 in $4020-$5FFF
+            0x4020..=0x5FFF if address & 0xE100 == 0x4100 => {
+                self.prg_bank = value.get(3);
+                self.chr_bank = value & 0b111;
+            }
+            _ => {}
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirroring {
+        self.cartridge.header.mirroring
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+}
+
+impl Clock for Nina003 {}
+
+impl Reset for Nina003 {
+    fn reset(&mut self) {
+        self.prg_bank = 0;
+        self.chr_bank = 0;
+    }
+}
+
+impl Snapshot for Nina003 {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.prg_bank);
+        state.write_u8(self.chr_bank);
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.prg_bank = state.read_u8()?;
+        self.chr_bank = state.read_u8()?;
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Nina003;
+    use crate::{
+        cartridge::{Cartridge, Header},
+        mappers::Mapper,
+    };
+
+    #[test]
+    fn test_nina003_banking() {
+        let mut mapper = Nina003::new(Cartridge {
+            header: Header {
+                prg_rom_pages: 4,
+                chr_rom_pages: 8,
+                mapper: 79,
+                ..Cartridge::default().header
+            },
+            prg_rom: (0..2).flat_map(|bank| vec![bank; 0x8000]).collect(),
+            chr_rom: (0..8).flat_map(|bank| vec![bank; 0x2000]).collect(),
+            ..Default::default()
+        });
+
+        mapper.write(0x4100, 0x0D);
+
+        assert_eq!(mapper.read(0x8000), Some(1));
+        assert_eq!(mapper.read(0x0000), Some(5));
+
+        // A8 is clear and the ROM space has no register
+        mapper.write(0x4200, 0x00);
+        mapper.write(0x8000, 0x00);
+
+        assert_eq!(mapper.read(0xFFFF), Some(1));
+        assert_eq!(mapper.read(0x1FFF), Some(5));
+
+        // mirrored every $200 bytes up to $5FFF
+        mapper.write(0x5F00, 0x03);
+
+        assert_eq!(mapper.read(0x8000), Some(0));
+        assert_eq!(mapper.read(0x0000), Some(3));
+    }
+}
@@ -0,0 +1,241 @@
+// https://www.nesdev.org/wiki/VRC7
+
+use super::{vrc7_audio::Vrc7Audio, vrc_irq::VrcIrq, Mapper};
+use crate::{
+    apu::ExpansionAudio,
+    cartridge::{Cartridge, ChrPage, Mirroring, PrgPage},
+    error::Error,
+    utils::{BitFlag, Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+#[derive(Debug)]
+pub struct Vrc7 {
+    cartridge: Cartridge,
+    prg_banks: [u8; 3],
+    chr_banks: [u8; 8],
+    control: u8,
+    irq: VrcIrq,
+    audio: Vrc7Audio,
+}
+
+impl Vrc7 {
+    pub fn new(cartridge: Cartridge) -> Self {
+        Self {
+            cartridge,
+            prg_banks: [0; 3],
+            chr_banks: [0; 8],
+            control: 0,
+            irq: VrcIrq::default(),
+            audio: Vrc7Audio::default(),
+        }
+    }
+
+    /// VRC7b (submapper 1) selects the second register with A3, VRC7a (submapper 2) with A4.
+    /// Both lines are decoded when the header has no submapper.
+    fn translate(&self, address: u16) -> u16 {
+        let mask = match self.cartridge.header.submapper {
+            1 => 0x08,
+            2 => 0x10,
+            _ => 0x18,
+        };
+
+        (address & 0xF000) | (address & mask != 0) as u16
+    }
+
+    fn prg_page(&self, address: u16) -> PrgPage {
+        let count = (self.cartridge.header.prg_rom_pages as usize * 2).max(1);
+        let bank = match address {
+            0x8000..=0x9FFF => self.prg_banks[0] as usize,
+            0xA000..=0xBFFF => self.prg_banks[1] as usize,
+            0xC000..=0xDFFF => self.prg_banks[2] as usize,
+            _ => count - 1,
+        };
+
+        PrgPage::Index8(bank % count)
+    }
+
+    fn chr_page(&self, address: u16) -> ChrPage {
+        let count = match self.cartridge.header.chr_rom_pages {
+            0 => 8,
+            pages => pages as usize * 8,
+        };
+        let bank = self.chr_banks[address as usize >> 10];
+
+        ChrPage::Index1(bank as usize % count)
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.control.contains(7)
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        // the audio ports are only wired on VRC7a, which uses A4 and A5
+        match address & 0xF030 {
+            0x9010 => return self.audio.write_address(value),
+            0x9030 => return self.audio.write_data(value),
+            _ => {}
+        }
+
+        match self.translate(address) {
+            0x8000 => self.prg_banks[0] = value & 0x3F,
+            0x8001 => self.prg_banks[1] = value & 0x3F,
+            0x9000 => self.prg_banks[2] = value & 0x3F,
+            address @ 0xA000..=0xD001 => {
+                let index = ((address - 0xA000) >> 12) * 2 + (address & 1);
+                self.chr_banks[index as usize] = value;
+            }
+            0xE000 => {
+                self.control = value;
+                self.audio.set_silenced(value.contains(6));
+            }
+            0xE001 => self.irq.write_latch(value),
+            0xF000 => self.irq.write_control(value),
+            0xF001 => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+}
+
+impl Mapper for Vrc7 {
+    fn read(&self, address: u16) -> Option<u8> {
+        Some(match address {
+            0x0000..=0x1FFF => self.cartridge.read_chr(address, self.chr_page(address)),
+            0x6000..=0x7FFF if self.prg_ram_enabled() => self.cartridge.read_prg_ram(address),
+            0x8000..=0xFFFF => self.cartridge.read_prg_rom(address, self.prg_page(address)),
+            _ => return None,
+        })
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => {
+                let page = self.chr_page(address);
+                self.cartridge.write_chr(address, value, page)
+            }
+            0x6000..=0x7FFF if self.prg_ram_enabled() => {
+                self.cartridge.write_prg_ram(address, value)
+            }
+            0x8000..=0xFFFF => self.write_register(address, value),
+            _ => {}
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirroring {
+        match self.control & 0b11 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::OneScreenLower,
+            _ => Mirroring::OneScreenUpper,
+        }
+    }
+
+    fn get_cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.irq()
+    }
+
+    fn expansion_audio(&self) -> Option<&dyn ExpansionAudio> {
+        Some(&self.audio)
+    }
+}
+
+impl Clock for Vrc7 {
+    fn tick(&mut self) {
+        self.irq.tick();
+        self.audio.tick();
+    }
+}
+
+impl Reset for Vrc7 {
+    fn reset(&mut self) {
+        self.prg_banks = [0; 3];
+        self.chr_banks = [0; 8];
+        self.control = 0;
+        self.irq = VrcIrq::default();
+        self.audio = Vrc7Audio::default();
+    }
+}
+
+impl Snapshot for Vrc7 {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_bytes(&self.prg_banks);
+        state.write_bytes(&self.chr_banks);
+        state.write_u8(self.control);
+        self.irq.save(state);
+        self.audio.save(state);
+        self.cartridge.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        state.read_bytes(&mut self.prg_banks)?;
+        state.read_bytes(&mut self.chr_banks)?;
+        self.control = state.read_u8()?;
+        self.irq.load(state)?;
+        self.audio.load(state)?;
+        self.cartridge.load(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Vrc7;
+    use crate::{
+        cartridge::Mirroring,
+        mappers::Mapper,
+        utils::{test::banked_cartridge, Clock},
+    };
+
+    fn vrc7(submapper: u8) -> Vrc7 {
+        let mut cartridge = banked_cartridge(85, 8, 32);
+        cartridge.header.submapper = submapper;
+        Vrc7::new(cartridge)
+    }
+
+    #[test]
+    fn test_vrc7_banking() {
+        for (submapper, high) in [(1, 0x08), (2, 0x10), (0, 0x08), (0, 0x10)] {
+            let mut mapper = vrc7(submapper);
+
+            mapper.write(0x8000, 2);
+            mapper.write(0x8000 | high, 5);
+            mapper.write(0x9000, 9);
+            mapper.write(0xB000 | high, 0x33);
+            mapper.write(0xE000, 0x81);
+            mapper.write(0x6000, 0x42);
+
+            assert_eq!(mapper.read(0x8000), Some(2));
+            assert_eq!(mapper.read(0xA000), Some(5));
+            assert_eq!(mapper.read(0xC000), Some(9));
+            assert_eq!(mapper.read(0xE000), Some(15));
+            assert_eq!(mapper.read(0x0C00), Some(0x33));
+            assert_eq!(mapper.read(0x6000), Some(0x42));
+            assert_eq!(mapper.get_mirroring(), Mirroring::Horizontal);
+        }
+    }
+
+    #[test]
+    fn test_vrc7_irq() {
+        let mut mapper = vrc7(2);
+
+        mapper.write(0xE010, 0xFE);
+        mapper.write(0xF000, 0b110);
+        mapper.tick();
+
+        assert!(!mapper.irq());
+
+        mapper.tick();
+
+        assert!(mapper.irq());
+
+        mapper.write(0xF010, 0);
+
+        assert!(!mapper.irq());
+    }
+}
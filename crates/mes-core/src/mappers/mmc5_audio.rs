@@ -0,0 +1,144 @@
+// https://www.nesdev.org/wiki/MMC5_audio
+
+use crate::{
+    apu::{
+        channels::{Channel, Pulse},
+        frame_counter::{ClockFrame, Frame},
+        AudioChip, ExpansionAudio,
+    },
+    error::Error,
+    utils::{BitFlag, Clock, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+// envelopes and length counters are clocked at a fixed 240 Hz
+const FRAME_PERIOD: u16 = 7457;
+
+#[derive(Debug)]
+pub struct Mmc5Audio {
+    pulse1: Pulse,
+    pulse2: Pulse,
+    pcm: u8,
+    pcm_read_mode: bool,
+    pcm_irq_enabled: bool,
+    pcm_interrupt: bool,
+    divider: u16,
+    cycle: u64,
+}
+
+impl Default for Mmc5Audio {
+    fn default() -> Self {
+        Self {
+            pulse1: Pulse::channel2(),
+            pulse2: Pulse::channel2(),
+            pcm: 0,
+            pcm_read_mode: false,
+            pcm_irq_enabled: false,
+            pcm_interrupt: false,
+            divider: 0,
+            cycle: 0,
+        }
+    }
+}
+
+impl Mmc5Audio {
+    pub fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0x5001 | 0x5005 => {} // no sweep units
+            0x5000..=0x5003 => self.pulse1.write_register(address, value),
+            0x5004..=0x5007 => self.pulse2.write_register(address, value),
+            0x5010 => {
+                self.pcm_read_mode = value.contains(0);
+                self.pcm_irq_enabled = value.contains(7);
+            }
+            0x5011 if !self.pcm_read_mode && value != 0 => self.pcm = value,
+            0x5015 => {
+                self.pulse1.set_enabled(value.contains(0));
+                self.pulse2.set_enabled(value.contains(1));
+            }
+            _ => {}
+        }
+    }
+
+    pub fn read_status(&self) -> u8 {
+        (self.pulse1.is_active() as u8) | (self.pulse2.is_active() as u8) << 1
+    }
+
+    pub fn read_pcm_status(&self) -> u8 {
+        (self.pcm_interrupt as u8) << 7
+    }
+
+    pub fn acknowledge_pcm(&mut self) {
+        self.pcm_interrupt = false;
+    }
+
+    /// Captures a CPU read from $8000-$BFFF in PCM read mode.
+    pub fn on_prg_read(&mut self, value: u8) {
+        if !self.pcm_read_mode {
+            return;
+        }
+
+        match value {
+            0 => self.pcm_interrupt = self.pcm_irq_enabled,
+            value => self.pcm = value,
+        }
+    }
+
+    pub fn irq(&self) -> bool {
+        self.pcm_interrupt
+    }
+}
+
+impl ExpansionAudio for Mmc5Audio {
+    fn chip(&self) -> AudioChip {
+        AudioChip::Mmc5
+    }
+
+    fn get_sample(&self) -> f32 {
+        let pulse = (self.pulse1.get_sample() + self.pulse2.get_sample()) / 30.0;
+        (pulse + self.pcm as f32 / 255.0) / 2.0
+    }
+}
+
+impl Clock for Mmc5Audio {
+    fn tick(&mut self) {
+        if self.cycle % 2 == 1 {
+            self.pulse1.tick();
+            self.pulse2.tick();
+        }
+
+        self.divider += 1;
+
+        if self.divider == FRAME_PERIOD {
+            self.divider = 0;
+            self.pulse1.tick_frame(&Frame::Half);
+            self.pulse2.tick_frame(&Frame::Half);
+        }
+
+        self.cycle += 1;
+    }
+}
+
+impl Snapshot for Mmc5Audio {
+    fn save(&self, state: &mut SnapshotWriter) {
+        self.pulse1.save(state);
+        self.pulse2.save(state);
+        state.write_u8(self.pcm);
+        state.write_bool(self.pcm_read_mode);
+        state.write_bool(self.pcm_irq_enabled);
+        state.write_bool(self.pcm_interrupt);
+        state.write_u16(self.divider);
+        state.write_u64(self.cycle);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.pulse1.load(state)?;
+        self.pulse2.load(state)?;
+        self.pcm = state.read_u8()?;
+        self.pcm_read_mode = state.read_bool()?;
+        self.pcm_irq_enabled = state.read_bool()?;
+        self.pcm_interrupt = state.read_bool()?;
+        self.divider = state.read_u16()? % FRAME_PERIOD;
+        self.cycle = state.read_u64()?;
+        Ok(())
+    }
+}
@@ -2,21 +2,150 @@ mod mapper_000;
 mod mapper_001;
 mod mapper_002;
 mod mapper_003;
+mod mapper_004;
+mod mapper_005;
+mod mapper_007;
+mod mapper_009;
+mod mapper_010;
+mod mapper_011;
+mod mapper_019;
+mod mapper_021;
+mod mapper_024;
+mod mapper_030;
+mod mapper_034;
+mod mapper_066;
+mod mapper_069;
+mod mapper_071;
+mod mapper_079;
+mod mapper_085;
+mod mmc5_audio;
+mod namco163_audio;
+mod sunsoft_5b;
+mod vrc6_audio;
+mod vrc7_audio;
+mod vrc_irq;
 
-use self::{mapper_000::NRom, mapper_001::SxRom, mapper_002::UxRom, mapper_003::CnRom};
+use self::{
+    mapper_000::NRom, mapper_001::SxRom, mapper_002::UxRom, mapper_003::CnRom, mapper_004::TxRom,
+    mapper_005::Mmc5, mapper_007::AxRom, mapper_009::PxRom, mapper_010::FxRom,
+    mapper_011::ColorDreams, mapper_019::Namco163, mapper_021::Vrc4, mapper_024::Vrc6,
+    mapper_030::Unrom512, mapper_034::BnRom, mapper_066::GxRom, mapper_069::Fme7,
+    mapper_071::Camerica, mapper_079::Nina003, mapper_085::Vrc7,
+};
 
 use crate::{
-    cartridge::{Cartridge, Mirroring},
+    apu::{AudioChip, ExpansionAudio},
+    cartridge::{Cartridge, Header, Mirroring},
     error::Error,
-    utils::{MemoryObserver, Reset},
+    utils::{Clock, MemoryObserver, Reset, Snapshot, SnapshotReader, SnapshotWriter},
 };
 
-use std::{cell::RefCell, fmt::Debug, rc::Rc};
+use std::{
+    cell::{Ref, RefCell},
+    fmt::Debug,
+    rc::Rc,
+};
 
-pub trait Mapper: Debug + Reset {
-    fn read(&self, address: u16) -> u8;
+/// Cartridge hardware, ticked once per CPU cycle (M2).
+pub trait Mapper: Debug + Reset + Clock + Snapshot {
+    /// `None` when nothing on the cartridge drives the data bus at `address`.
+    fn read(&self, address: u16) -> Option<u8>;
     fn write(&mut self, address: u16, value: u8);
     fn get_mirroring(&self) -> Mirroring;
+    fn get_cartridge(&self) -> &Cartridge;
+    fn get_cartridge_mut(&mut self) -> &mut Cartridge;
+
+    /// Pattern table read made by the PPU, unlike `read` it can update the mapper state.
+    fn fetch_chr(&mut self, address: u16) -> u8 {
+        self.read(address).unwrap_or_default()
+    }
+
+    /// Called with every address the PPU puts on its bus.
+    fn on_ppu_address(&mut self, _address: u16) {}
+
+    /// Called after every CPU read of the cartridge space, for registers with read side effects.
+    fn on_cpu_read(&mut self, _address: u16) {}
+
+    /// CPU writes to $2000-$401F, which the cartridge sees on the bus but does not decode.
+    fn on_cpu_write(&mut self, _address: u16, _value: u8) {}
+
+    /// Storage behind a $2000-$2FFF nametable access, the default follows `get_mirroring`.
+    fn get_nametable(&self, address: u16) -> Nametable {
+        let page = match self.get_mirroring() {
+            Mirroring::Horizontal => address >> 11 & 1,
+            Mirroring::OneScreenLower => 0,
+            Mirroring::OneScreenUpper => 1,
+            Mirroring::Vertical => address >> 10 & 1,
+            // the upper two nametables are backed by the cartridge VRAM
+            Mirroring::FourScreen if address & 0x0800 != 0 => return Nametable::Mapper,
+            Mirroring::FourScreen => address >> 10 & 1,
+        };
+
+        Nametable::Ciram(page as u8)
+    }
+
+    fn read_nametable(&self, address: u16) -> u8 {
+        self.get_cartridge().read_vram(address)
+    }
+
+    fn write_nametable(&mut self, address: u16, value: u8) {
+        self.get_cartridge_mut().write_vram(address, value)
+    }
+
+    /// CIRAM page mapped in place of the pattern table at `address`, if any.
+    fn get_chr_ciram(&self, _address: u16) -> Option<u8> {
+        None
+    }
+
+    /// State of the cartridge IRQ output, shared with the APU on the CPU /IRQ line.
+    fn irq(&self) -> bool {
+        false
+    }
+
+    fn expansion_audio(&self) -> Option<&dyn ExpansionAudio> {
+        None
+    }
+
+    /// Writes to cartridge RAM without touching the mapper registers.
+    fn poke(&mut self, address: u16, value: u8) {
+        let cartridge = self.get_cartridge_mut();
+
+        if (0x6000..=0x7FFF).contains(&address) && !cartridge.prg_ram.is_empty() {
+            cartridge.write_prg_ram(address, value);
+        }
+    }
+
+    /// Non-volatile memory that should be persisted, if the board has a battery.
+    fn battery_ram(&self) -> Option<&[u8]> {
+        self.get_cartridge().battery_ram()
+    }
+
+    fn load_battery_ram(&mut self, bytes: &[u8]) {
+        self.get_cartridge_mut().load_battery_ram(bytes)
+    }
+
+    fn take_battery_ram_dirty(&mut self) -> bool {
+        self.get_cartridge_mut().take_prg_ram_dirty()
+    }
+}
+
+/// Submapper 1 marks discrete boards without bus conflicts and submapper 2 boards with them.
+fn has_bus_conflicts(header: &Header) -> bool {
+    header.submapper == 2
+}
+
+/// The ROM drives the data bus along with the CPU during a register write on discrete boards,
+/// so the latched value is ANDed with the byte at the written address.
+fn bus_conflict(mapper: &impl Mapper, address: u16, value: u8) -> u8 {
+    value & mapper.read(address).unwrap_or(value)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nametable {
+    /// One of the two 1 KB pages of the console VRAM.
+    Ciram(u8),
+    /// Supplied by `Mapper::read_nametable` and `Mapper::write_nametable`.
+    Mapper,
 }
 
 pub struct MapperBuilder {
@@ -47,7 +176,7 @@ impl MapperBuilder {
 pub struct MapperChip(Rc<RefCell<dyn Mapper>>);
 
 impl MapperChip {
-    fn new<M: Mapper + 'static>(mapper: M) -> Self {
+    pub fn new<M: Mapper + 'static>(mapper: M) -> Self {
         Self(Rc::new(RefCell::new(mapper)))
     }
 
@@ -60,6 +189,81 @@ impl MapperChip {
     pub fn try_from_bytes(bytes: &[u8]) -> Result<Self, Error> {
         Cartridge::try_from_bytes(bytes).and_then(MapperChip::try_from)
     }
+
+    pub fn read(&self, address: u16) -> Option<u8> {
+        self.0.borrow().read(address)
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) {
+        self.0.borrow_mut().write(address, value)
+    }
+
+    pub fn get_mirroring(&self) -> Mirroring {
+        self.0.borrow().get_mirroring()
+    }
+
+    pub fn fetch_chr(&mut self, address: u16) -> u8 {
+        self.0.borrow_mut().fetch_chr(address)
+    }
+
+    pub fn on_ppu_address(&mut self, address: u16) {
+        self.0.borrow_mut().on_ppu_address(address)
+    }
+
+    pub fn on_cpu_read(&mut self, address: u16) {
+        self.0.borrow_mut().on_cpu_read(address)
+    }
+
+    pub fn on_cpu_write(&mut self, address: u16, value: u8) {
+        self.0.borrow_mut().on_cpu_write(address, value)
+    }
+
+    pub fn get_nametable(&self, address: u16) -> Nametable {
+        self.0.borrow().get_nametable(address)
+    }
+
+    pub fn read_nametable(&self, address: u16) -> u8 {
+        self.0.borrow().read_nametable(address)
+    }
+
+    pub fn write_nametable(&mut self, address: u16, value: u8) {
+        self.0.borrow_mut().write_nametable(address, value)
+    }
+
+    pub fn get_chr_ciram(&self, address: u16) -> Option<u8> {
+        self.0.borrow().get_chr_ciram(address)
+    }
+
+    pub fn irq(&self) -> bool {
+        self.0.borrow().irq()
+    }
+
+    pub fn expansion_audio(&self) -> Option<(AudioChip, f32)> {
+        let mapper = self.0.borrow();
+        let audio = mapper.expansion_audio()?;
+
+        Some((audio.chip(), audio.get_sample()))
+    }
+
+    pub fn poke(&mut self, address: u16, value: u8) {
+        self.0.borrow_mut().poke(address, value)
+    }
+
+    pub fn battery_ram(&self) -> Option<Ref<[u8]>> {
+        Ref::filter_map(self.0.borrow(), |mapper| mapper.battery_ram()).ok()
+    }
+
+    pub fn load_battery_ram(&mut self, bytes: &[u8]) {
+        self.0.borrow_mut().load_battery_ram(bytes)
+    }
+
+    pub fn take_battery_ram_dirty(&mut self) -> bool {
+        self.0.borrow_mut().take_battery_ram_dirty()
+    }
+
+    pub fn get_cartridge(&self) -> Ref<Cartridge> {
+        Ref::map(self.0.borrow(), |mapper| mapper.get_cartridge())
+    }
 }
 
 impl TryFrom<Cartridge> for MapperChip {
@@ -71,22 +275,40 @@ impl TryFrom<Cartridge> for MapperChip {
             1 => Ok(Self::new(SxRom::new(value))),
             2 => Ok(Self::new(UxRom::new(value))),
             3 => Ok(Self::new(CnRom::new(value))),
+            4 => Ok(Self::new(TxRom::new(value))),
+            5 => Ok(Self::new(Mmc5::new(value))),
+            7 => Ok(Self::new(AxRom::new(value))),
+            9 => Ok(Self::new(PxRom::new(value))),
+            10 => Ok(Self::new(FxRom::new(value))),
+            11 => Ok(Self::new(ColorDreams::new(value))),
+            19 => Ok(Self::new(Namco163::new(value))),
+            21 | 22 | 23 | 25 => Ok(Self::new(Vrc4::new(value))),
+            24 | 26 => Ok(Self::new(Vrc6::new(value))),
+            30 => Ok(Self::new(Unrom512::new(value))),
+            34 => Ok(Self::new(BnRom::new(value))),
+            66 => Ok(Self::new(GxRom::new(value))),
+            69 => Ok(Self::new(Fme7::new(value))),
+            71 => Ok(Self::new(Camerica::new(value))),
+            79 => Ok(Self::new(Nina003::new(value))),
+            85 => Ok(Self::new(Vrc7::new(value))),
             id => Err(Error::UnsupportedMapper(id)),
         }
     }
 }
 
-impl Mapper for MapperChip {
-    fn read(&self, address: u16) -> u8 {
-        self.0.borrow().read(address)
+impl Clock for MapperChip {
+    fn tick(&mut self) {
+        self.0.borrow_mut().tick()
     }
+}
 
-    fn write(&mut self, address: u16, value: u8) {
-        self.0.borrow_mut().write(address, value)
+impl Snapshot for MapperChip {
+    fn save(&self, state: &mut SnapshotWriter) {
+        self.0.borrow().save(state)
     }
 
-    fn get_mirroring(&self) -> Mirroring {
-        self.0.borrow().get_mirroring()
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.0.borrow_mut().load(state)
     }
 }
 
@@ -95,3 +317,90 @@ impl Reset for MapperChip {
         self.0.borrow_mut().reset()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::MapperChip;
+    use crate::{
+        cartridge::Header,
+        utils::{test::XorShift, Clock},
+    };
+
+    const PAGE_COUNTS: [u8; 10] = [0, 1, 2, 3, 4, 16, 32, 64, 128, 255];
+
+    /// Builds a ROM with a random header for `mapper` and a matching body.
+    fn random_rom(rng: &mut XorShift, mapper: u16) -> Option<Vec<u8>> {
+        let mut header = [0; 16];
+
+        header[..4].copy_from_slice(b"NES\x1A");
+        header[4] = PAGE_COUNTS[rng.below(PAGE_COUNTS.len())];
+        header[5] = PAGE_COUNTS[rng.below(PAGE_COUNTS.len())];
+        header[6] = (mapper << 4) as u8 | (rng.next_u8() & 0x0F);
+        header[7] = (mapper & 0xF0) as u8 | (rng.next_u8() & 0x0F);
+
+        if rng.below(2) == 0 {
+            header[8..].copy_from_slice(&rng.bytes(8));
+            header[8] &= 0xF0; // keeps the NES 2.0 mapper MSB at zero
+            header[9] &= if rng.below(4) == 0 { 0xFF } else { 0x00 };
+        }
+
+        let header_info = Header::try_from_bytes(&header).ok()?;
+        let size = 512 + header_info.prg_rom_size + header_info.chr_rom_size;
+        let mut rom = header.to_vec();
+
+        if size > 0x800000 {
+            return None;
+        }
+
+        rom.resize(16 + size, rng.next_u8());
+        rom.iter_mut()
+            .skip(16)
+            .step_by(97)
+            .for_each(|byte| *byte = rng.next_u8());
+
+        Some(rom)
+    }
+
+    #[test]
+    fn test_random_roms() {
+        let mut rng = XorShift::new(0x6D65_735F_726F_6D73);
+        let mappers = [
+            0, 1, 2, 3, 4, 5, 7, 9, 10, 11, 19, 21, 22, 23, 24, 25, 26, 30, 34, 66, 69, 71, 79, 85,
+        ];
+
+        for mapper in mappers {
+            for _ in 0..16 {
+                let Some(rom) = random_rom(&mut rng, mapper) else {
+                    continue;
+                };
+
+                let truncated = rng.below(rom.len());
+                let _ = MapperChip::try_from_bytes(&rom[..truncated]);
+
+                let Ok(mut chip) = MapperChip::try_from_bytes(&rom) else {
+                    continue;
+                };
+
+                for _ in 0..5000 {
+                    let address = rng.next_u16();
+
+                    match rng.below(4) {
+                        0 => chip.write(address | 0x4020, rng.next_u8()),
+                        1 => {
+                            chip.fetch_chr(address & 0x1FFF);
+                        }
+                        2 => {
+                            chip.read_nametable(0x2000 | address & 0x0FFF);
+                        }
+                        _ => {
+                            chip.read(address);
+                        }
+                    }
+
+                    chip.on_ppu_address(address & 0x3FFF);
+                    chip.tick();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+// https://www.nesdev.org/wiki/Namco_163_audio
+
+use crate::{
+    apu::{AudioChip, ExpansionAudio},
+    error::Error,
+    utils::{BitFlag, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+// a single channel is updated every 15 CPU cycles
+const UPDATE_PERIOD: u8 = 15;
+const CHANNEL_REGISTERS: usize = 0x40;
+const CHANNEL_COUNT: usize = 8;
+// 15 * 15
+const MAX_OUTPUT: f32 = 225.0;
+
+#[derive(Debug, Default)]
+pub struct Namco163Audio {
+    outputs: [u8; CHANNEL_COUNT],
+    active: u8,
+    channel: u8,
+    divider: u8,
+    disabled: bool,
+}
+
+impl Namco163Audio {
+    pub fn set_disabled(&mut self, disabled: bool) {
+        self.disabled = disabled;
+    }
+
+    /// Advances by one CPU cycle, the channel registers and phases live in the sound RAM.
+    pub fn clock(&mut self, ram: &mut [u8]) {
+        if self.disabled {
+            return;
+        }
+
+        self.divider += 1;
+
+        if self.divider < UPDATE_PERIOD {
+            return;
+        }
+
+        // channels are updated from 7 down to 8 - active
+        let active = ram[0x7F].get_range(4..7) + 1;
+        let last = CHANNEL_COUNT as u8 - active;
+        let channel = self.channel.max(last);
+
+        self.outputs[channel as usize] = update_channel(ram, channel as usize);
+        self.channel = if channel == last { 7 } else { channel - 1 };
+        self.active = active;
+        self.divider = 0;
+    }
+}
+
+fn update_channel(ram: &mut [u8], channel: usize) -> u8 {
+    let registers = &mut ram[CHANNEL_REGISTERS + channel * 8..][..8];
+    let frequency = u32::from_le_bytes([registers[0], registers[2], registers[4] & 0b11, 0]);
+    let length = 256 - (registers[4] & 0xFC) as u32;
+    let phase = u32::from_le_bytes([registers[1], registers[3], registers[5], 0]);
+    let phase = (phase + frequency) % (length << 16);
+    let [low, mid, high, _] = phase.to_le_bytes();
+
+    (registers[1], registers[3], registers[5]) = (low, mid, high);
+
+    let offset = registers[6] as usize;
+    let volume = registers[7] & 0x0F;
+    let address = ((phase >> 16) as usize + offset) & 0xFF;
+    let sample = (ram[address / 2] >> ((address & 1) * 4)) & 0x0F;
+
+    sample * volume
+}
+
+impl ExpansionAudio for Namco163Audio {
+    fn chip(&self) -> AudioChip {
+        AudioChip::Namco163
+    }
+
+    fn get_sample(&self) -> f32 {
+        if self.disabled || self.active == 0 {
+            return 0.0;
+        }
+
+        // the channels are time multiplexed, so the output averages them
+        let first = CHANNEL_COUNT - self.active as usize;
+        let output = self.outputs[first..].iter().map(|&o| o as f32).sum::<f32>();
+
+        output / (self.active as f32 * MAX_OUTPUT)
+    }
+}
+
+impl Snapshot for Namco163Audio {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_bytes(&self.outputs);
+        state.write_u8(self.active);
+        state.write_u8(self.channel);
+        state.write_u8(self.divider);
+        state.write_bool(self.disabled);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        state.read_bytes(&mut self.outputs)?;
+        self.active = state.read_u8()?.min(CHANNEL_COUNT as u8);
+        self.channel = state.read_u8()? & 0x07;
+        self.divider = state.read_u8()? % UPDATE_PERIOD;
+        self.disabled = state.read_bool()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Namco163Audio, MAX_OUTPUT, UPDATE_PERIOD};
+    use crate::apu::ExpansionAudio;
+
+    #[test]
+    fn test_wavetable_channel() {
+        let mut audio = Namco163Audio::default();
+        let mut ram = [0; 128];
+
+        ram[0] = 0xF0; // samples 0 and 15
+        ram[0x7C] = 0xFD; // 4 samples long, one sample per update
+        ram[0x7F] = 0x0A; // one channel, volume 10
+
+        let samples = (0..4)
+            .map(|_| {
+                for _ in 0..UPDATE_PERIOD {
+                    audio.clock(&mut ram);
+                }
+
+                (audio.get_sample() * MAX_OUTPUT).round() as u8
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(samples, [150, 0, 0, 0]);
+        assert_eq!(ram[0x7D], 0);
+    }
+}
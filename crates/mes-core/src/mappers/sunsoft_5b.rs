@@ -0,0 +1,301 @@
+// https://www.nesdev.org/wiki/Sunsoft_5B_audio
+
+use crate::{
+    apu::{channels::Timer, AudioChip, ExpansionAudio},
+    error::Error,
+    utils::{BitFlag, Clock, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+// the chip runs at M2 / 2 and its generators are clocked every 8 of its own cycles
+const CLOCK_DIVIDER: u8 = 16;
+const ENVELOPE_STEPS: u8 = 32;
+
+#[derive(Debug, Default)]
+struct Envelope {
+    timer: Timer,
+    shape: u8,
+    step: u8,
+    attack: bool,
+    holding: bool,
+}
+
+impl Envelope {
+    fn write_shape(&mut self, value: u8) {
+        self.shape = value & 0x0F;
+        self.step = 0;
+        self.attack = self.shape.contains(2);
+        self.holding = false;
+    }
+
+    fn level(&self) -> u8 {
+        match self.attack {
+            true => self.step,
+            false => ENVELOPE_STEPS - 1 - self.step,
+        }
+    }
+
+    fn clock(&mut self) {
+        if self.holding {
+            return;
+        }
+
+        self.step += 1;
+
+        if self.step < ENVELOPE_STEPS {
+            return;
+        }
+
+        let (hold, alternate, continue_) = (
+            self.shape.contains(0),
+            self.shape.contains(1),
+            self.shape.contains(3),
+        );
+
+        if !continue_ {
+            self.holding = true;
+            self.attack = false;
+            self.step = ENVELOPE_STEPS - 1;
+        } else if hold {
+            self.holding = true;
+            self.attack ^= alternate;
+            self.step = ENVELOPE_STEPS - 1;
+        } else {
+            self.attack ^= alternate;
+            self.step = 0;
+        }
+    }
+}
+
+impl Snapshot for Envelope {
+    fn save(&self, state: &mut SnapshotWriter) {
+        self.timer.save(state);
+        state.write_u8(self.shape);
+        state.write_u8(self.step);
+        state.write_bool(self.attack);
+        state.write_bool(self.holding);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.timer.load(state)?;
+        self.shape = state.read_u8()?;
+        self.step = state.read_u8()? % ENVELOPE_STEPS;
+        self.attack = state.read_bool()?;
+        self.holding = state.read_bool()?;
+        Ok(())
+    }
+}
+
+/// YM2149 derived sound chip of the Sunsoft FME-7 variant used by Gimmick!
+#[derive(Debug)]
+pub struct Sunsoft5B {
+    select: u8,
+    registers: [u8; 16],
+    tones: [Timer; 3],
+    outputs: [bool; 3],
+    noise: Timer,
+    noise_half: bool,
+    lfsr: u32,
+    envelope: Envelope,
+    divider: u8,
+}
+
+impl Default for Sunsoft5B {
+    fn default() -> Self {
+        Self {
+            select: 0,
+            registers: [0; 16],
+            tones: Default::default(),
+            outputs: [false; 3],
+            noise: Timer::default(),
+            noise_half: false,
+            lfsr: 1,
+            envelope: Envelope::default(),
+            divider: 0,
+        }
+    }
+}
+
+impl Sunsoft5B {
+    pub fn write_select(&mut self, value: u8) {
+        self.select = value & 0x0F;
+    }
+
+    pub fn write_data(&mut self, value: u8) {
+        let index = self.select as usize;
+        self.registers[index] = value;
+
+        match index {
+            0x00..=0x05 => {
+                let channel = index / 2;
+                let fine = self.registers[channel * 2] as u16;
+                let coarse = (self.registers[channel * 2 + 1] & 0x0F) as u16;
+                self.tones[channel].period = ((coarse << 8) | fine).max(1) - 1;
+            }
+            0x06 => self.noise.period = (value & 0x1F).max(1) as u16 - 1,
+            0x0B | 0x0C => {
+                let period = u16::from_le_bytes([self.registers[0x0B], self.registers[0x0C]]);
+                self.envelope.timer.period = period.max(1) - 1;
+            }
+            0x0D => self.envelope.write_shape(value),
+            _ => {}
+        }
+    }
+
+    fn channel_volume(&self, channel: usize) -> f32 {
+        let volume = self.registers[0x08 + channel];
+        let level = match volume.contains(4) {
+            true => self.envelope.level(),
+            false if volume & 0x0F == 0 => 0,
+            false => (volume & 0x0F) * 2 + 1,
+        };
+
+        // 1.5 dB per step on the 5-bit scale
+        match level {
+            0 => 0.0,
+            level => 10f32.powf((level as f32 - 31.0) * 1.5 / 20.0),
+        }
+    }
+
+    fn clock_generators(&mut self) {
+        for (tone, output) in self.tones.iter_mut().zip(&mut self.outputs) {
+            tone.tick();
+
+            if tone.is_zero() {
+                *output = !*output;
+            }
+        }
+
+        self.noise_half = !self.noise_half;
+
+        if self.noise_half {
+            self.noise.tick();
+
+            if self.noise.is_zero() {
+                let feedback = (self.lfsr ^ (self.lfsr >> 3)) & 1;
+                self.lfsr = (self.lfsr >> 1) | (feedback << 16);
+            }
+        }
+
+        self.envelope.timer.tick();
+
+        if self.envelope.timer.is_zero() {
+            self.envelope.clock();
+        }
+    }
+}
+
+impl ExpansionAudio for Sunsoft5B {
+    fn chip(&self) -> AudioChip {
+        AudioChip::Sunsoft5B
+    }
+
+    fn get_sample(&self) -> f32 {
+        let mixer = self.registers[0x07];
+        let noise = self.lfsr & 1 == 1;
+
+        (0..3)
+            .filter(|&channel| {
+                (self.outputs[channel] || mixer.contains(channel as u8))
+                    && (noise || mixer.contains(channel as u8 + 3))
+            })
+            .map(|channel| self.channel_volume(channel))
+            .sum::<f32>()
+            / 3.0
+    }
+}
+
+impl Clock for Sunsoft5B {
+    fn tick(&mut self) {
+        self.divider += 1;
+
+        if self.divider == CLOCK_DIVIDER {
+            self.divider = 0;
+            self.clock_generators();
+        }
+    }
+}
+
+impl Snapshot for Sunsoft5B {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.select);
+        state.write_bytes(&self.registers);
+
+        for (tone, output) in self.tones.iter().zip(self.outputs) {
+            tone.save(state);
+            state.write_bool(output);
+        }
+
+        self.noise.save(state);
+        state.write_bool(self.noise_half);
+        state.write_u32(self.lfsr);
+        self.envelope.save(state);
+        state.write_u8(self.divider);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.select = state.read_u8()? & 0x0F;
+        state.read_bytes(&mut self.registers)?;
+
+        for (tone, output) in self.tones.iter_mut().zip(&mut self.outputs) {
+            tone.load(state)?;
+            *output = state.read_bool()?;
+        }
+
+        self.noise.load(state)?;
+        self.noise_half = state.read_bool()?;
+        self.lfsr = state.read_u32()?;
+        self.envelope.load(state)?;
+        self.divider = state.read_u8()? % CLOCK_DIVIDER;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sunsoft5B;
+    use crate::{apu::ExpansionAudio, utils::Clock};
+
+    fn write(chip: &mut Sunsoft5B, register: u8, value: u8) {
+        chip.write_select(register);
+        chip.write_data(value);
+    }
+
+    #[test]
+    fn test_tone_square_wave() {
+        let mut chip = Sunsoft5B::default();
+
+        write(&mut chip, 0x00, 2); // tone A period
+        write(&mut chip, 0x07, 0b111_110); // tone A only
+        write(&mut chip, 0x08, 0x0F);
+
+        let samples = (0..16 * 8)
+            .map(|_| {
+                chip.tick();
+                chip.get_sample()
+            })
+            .collect::<Vec<_>>();
+
+        let high = samples.iter().filter(|&&sample| sample > 0.0).count();
+
+        assert_eq!(high, 16 * 4);
+        assert!(samples.iter().all(|&sample| sample <= 1.0 / 3.0));
+    }
+
+    #[test]
+    fn test_envelope_hold() {
+        let mut chip = Sunsoft5B::default();
+
+        write(&mut chip, 0x07, 0b111_111); // gates always open
+        write(&mut chip, 0x08, 0x10); // tone A uses the envelope
+        write(&mut chip, 0x0B, 1);
+        write(&mut chip, 0x0D, 0b1101); // attack and hold
+
+        assert_eq!(chip.get_sample(), 0.0);
+
+        for _ in 0..16 * 64 {
+            chip.tick();
+        }
+
+        assert_eq!(chip.get_sample(), 1.0 / 3.0);
+    }
+}
@@ -0,0 +1,333 @@
+// https://www.nesdev.org/wiki/VRC6_audio
+
+use crate::{
+    apu::{
+        channels::{Sequencer, Timer},
+        AudioChip, ExpansionAudio,
+    },
+    error::Error,
+    utils::{BitFlag, Clock, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+// 15 + 15 + 31
+const MAX_OUTPUT: f32 = 61.0;
+
+#[derive(Debug)]
+struct Pulse {
+    timer: Timer,
+    sequencer: Sequencer,
+    period: u16,
+    duty: u8,
+    volume: u8,
+    constant: bool,
+    enabled: bool,
+}
+
+impl Pulse {
+    fn new() -> Self {
+        Self {
+            timer: Timer::default(),
+            sequencer: Sequencer::new(16),
+            period: 0,
+            duty: 0,
+            volume: 0,
+            constant: false,
+            enabled: false,
+        }
+    }
+
+    fn write_register(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.volume = value & 0x0F;
+                self.duty = value.get_range(4..7);
+                self.constant = value.contains(7);
+            }
+            1 => self.period = (self.period & 0x0F00) | value as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | ((value as u16 & 0x0F) << 8);
+                self.enabled = value.contains(7);
+
+                if !self.enabled {
+                    self.sequencer.reset();
+                }
+            }
+        }
+    }
+
+    fn clock(&mut self) {
+        self.timer.tick();
+
+        if self.timer.is_zero() && self.enabled {
+            self.sequencer.step();
+        }
+    }
+
+    fn get_sample(&self) -> u8 {
+        // the sequencer counts down on hardware, the output is high for duty + 1 steps
+        let step = 15 - self.sequencer.index() as u8;
+
+        match self.enabled && (self.constant || step <= self.duty) {
+            true => self.volume,
+            false => 0,
+        }
+    }
+}
+
+impl Snapshot for Pulse {
+    fn save(&self, state: &mut SnapshotWriter) {
+        self.timer.save(state);
+        self.sequencer.save(state);
+        state.write_u16(self.period);
+        state.write_u8(self.duty);
+        state.write_u8(self.volume);
+        state.write_bool(self.constant);
+        state.write_bool(self.enabled);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.timer.load(state)?;
+        self.sequencer.load(state)?;
+        self.period = state.read_u16()?;
+        self.duty = state.read_u8()?;
+        self.volume = state.read_u8()?;
+        self.constant = state.read_bool()?;
+        self.enabled = state.read_bool()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Sawtooth {
+    timer: Timer,
+    sequencer: Sequencer,
+    period: u16,
+    rate: u8,
+    accumulator: u8,
+    enabled: bool,
+}
+
+impl Sawtooth {
+    fn new() -> Self {
+        Self {
+            timer: Timer::default(),
+            sequencer: Sequencer::new(14),
+            period: 0,
+            rate: 0,
+            accumulator: 0,
+            enabled: false,
+        }
+    }
+
+    fn write_register(&mut self, register: u16, value: u8) {
+        match register {
+            0 => self.rate = value & 0x3F,
+            1 => self.period = (self.period & 0x0F00) | value as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | ((value as u16 & 0x0F) << 8);
+                self.enabled = value.contains(7);
+
+                if !self.enabled {
+                    self.sequencer.reset();
+                    self.accumulator = 0;
+                }
+            }
+        }
+    }
+
+    fn clock(&mut self) {
+        self.timer.tick();
+
+        if !self.timer.is_zero() || !self.enabled {
+            return;
+        }
+
+        // the accumulator is incremented on every other step and cleared on the 7th
+        self.sequencer.step();
+
+        match self.sequencer.index() {
+            0 => self.accumulator = 0,
+            step if step % 2 == 0 => self.accumulator = self.accumulator.wrapping_add(self.rate),
+            _ => {}
+        }
+    }
+
+    fn get_sample(&self) -> u8 {
+        self.accumulator >> 3
+    }
+}
+
+impl Snapshot for Sawtooth {
+    fn save(&self, state: &mut SnapshotWriter) {
+        self.timer.save(state);
+        self.sequencer.save(state);
+        state.write_u16(self.period);
+        state.write_u8(self.rate);
+        state.write_u8(self.accumulator);
+        state.write_bool(self.enabled);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.timer.load(state)?;
+        self.sequencer.load(state)?;
+        self.period = state.read_u16()?;
+        self.rate = state.read_u8()?;
+        self.accumulator = state.read_u8()?;
+        self.enabled = state.read_bool()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Vrc6Audio {
+    pulse1: Pulse,
+    pulse2: Pulse,
+    sawtooth: Sawtooth,
+    halt: bool,
+    shift: u8,
+}
+
+impl Default for Vrc6Audio {
+    fn default() -> Self {
+        Self {
+            pulse1: Pulse::new(),
+            pulse2: Pulse::new(),
+            sawtooth: Sawtooth::new(),
+            halt: false,
+            shift: 0,
+        }
+    }
+}
+
+impl Vrc6Audio {
+    /// Writes to $9000-$B002 with the board address lines already normalized.
+    pub fn write_register(&mut self, address: u16, value: u8) {
+        let register = address & 0b11;
+
+        match address {
+            0x9003 => {
+                self.halt = value.contains(0);
+                self.shift = match value & 0b110 {
+                    0 => 0,
+                    0b010 => 4,
+                    _ => 8,
+                };
+            }
+            0x9000..=0x9002 => self.pulse1.write_register(register, value),
+            0xA000..=0xA002 => self.pulse2.write_register(register, value),
+            0xB000..=0xB002 => self.sawtooth.write_register(register, value),
+            _ => return,
+        }
+
+        self.pulse1.timer.period = self.pulse1.period >> self.shift;
+        self.pulse2.timer.period = self.pulse2.period >> self.shift;
+        self.sawtooth.timer.period = self.sawtooth.period >> self.shift;
+    }
+}
+
+impl ExpansionAudio for Vrc6Audio {
+    fn chip(&self) -> AudioChip {
+        AudioChip::Vrc6
+    }
+
+    fn get_sample(&self) -> f32 {
+        let output = self.pulse1.get_sample() + self.pulse2.get_sample();
+        (output + self.sawtooth.get_sample()) as f32 / MAX_OUTPUT
+    }
+}
+
+impl Clock for Vrc6Audio {
+    fn tick(&mut self) {
+        if self.halt {
+            return;
+        }
+
+        self.pulse1.clock();
+        self.pulse2.clock();
+        self.sawtooth.clock();
+    }
+}
+
+impl Snapshot for Vrc6Audio {
+    fn save(&self, state: &mut SnapshotWriter) {
+        self.pulse1.save(state);
+        self.pulse2.save(state);
+        self.sawtooth.save(state);
+        state.write_bool(self.halt);
+        state.write_u8(self.shift);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.pulse1.load(state)?;
+        self.pulse2.load(state)?;
+        self.sawtooth.load(state)?;
+        self.halt = state.read_bool()?;
+        self.shift = state.read_u8()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Vrc6Audio, MAX_OUTPUT};
+    use crate::{apu::ExpansionAudio, utils::Clock};
+
+    #[test]
+    fn test_pulse_duty_cycle() {
+        let mut audio = Vrc6Audio::default();
+
+        audio.write_register(0x9000, 0b0011_1111); // 4/16 duty, volume 15
+        audio.write_register(0x9001, 0);
+        audio.write_register(0x9002, 0x80);
+
+        let high = (0..16)
+            .filter(|_| {
+                audio.tick();
+                audio.get_sample() > 0.0
+            })
+            .count();
+
+        assert_eq!(high, 4);
+    }
+
+    #[test]
+    fn test_sawtooth_accumulator() {
+        let mut audio = Vrc6Audio::default();
+
+        audio.write_register(0xB000, 42);
+        audio.write_register(0xB001, 0);
+        audio.write_register(0xB002, 0x80);
+
+        let samples = (0..14)
+            .map(|_| {
+                audio.tick();
+                (audio.get_sample() * MAX_OUTPUT).round() as u8
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            samples,
+            [0, 5, 5, 10, 10, 15, 15, 21, 21, 26, 26, 31, 31, 0]
+        );
+    }
+
+    #[test]
+    fn test_unused_registers() {
+        let mut audio = Vrc6Audio::default();
+
+        audio.write_register(0xA000, 0b0011_1111);
+        audio.write_register(0xA001, 0);
+        audio.write_register(0xA002, 0x80);
+        audio.write_register(0xA003, 0);
+        audio.write_register(0xB003, 0);
+
+        let high = (0..16)
+            .filter(|_| {
+                audio.tick();
+                audio.get_sample() > 0.0
+            })
+            .count();
+
+        assert_eq!(high, 4);
+    }
+}
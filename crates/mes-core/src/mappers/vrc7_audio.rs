@@ -0,0 +1,540 @@
+// https://www.nesdev.org/wiki/VRC7_audio
+
+use crate::{
+    apu::{AudioChip, ExpansionAudio},
+    error::Error,
+    utils::{BitFlag, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+// the 3.58 MHz chip outputs a sample every 72 of its cycles
+const CLOCK_DIVIDER: u8 = 36;
+const CHANNEL_COUNT: usize = 6;
+const REGISTER_COUNT: usize = 0x40;
+const MAX_ATTENUATION: u16 = 0x1FF;
+const MAX_OUTPUT: f32 = (CHANNEL_COUNT * 4096) as f32;
+
+// https://www.nesdev.org/wiki/VRC7_audio#Internal_patch_set
+const PATCHES: [[u8; 8]; 15] = [
+    [0x03, 0x21, 0x05, 0x06, 0xE8, 0x81, 0x42, 0x27],
+    [0x13, 0x41, 0x14, 0x0D, 0xD8, 0xF6, 0x23, 0x12],
+    [0x11, 0x11, 0x08, 0x08, 0xFA, 0xB2, 0x20, 0x12],
+    [0x31, 0x61, 0x0C, 0x07, 0xA8, 0x64, 0x61, 0x27],
+    [0x32, 0x21, 0x1E, 0x06, 0xE1, 0x76, 0x01, 0x28],
+    [0x02, 0x01, 0x06, 0x00, 0xA3, 0xE2, 0xF4, 0xF4],
+    [0x21, 0x61, 0x1D, 0x07, 0x82, 0x81, 0x11, 0x07],
+    [0x23, 0x21, 0x22, 0x17, 0xA2, 0x72, 0x01, 0x17],
+    [0x35, 0x11, 0x25, 0x00, 0x40, 0x73, 0x72, 0x01],
+    [0xB5, 0x01, 0x0F, 0x0F, 0xA8, 0xA5, 0x51, 0x02],
+    [0x17, 0xC1, 0x24, 0x07, 0xF8, 0xF8, 0x22, 0x12],
+    [0x71, 0x23, 0x11, 0x06, 0x65, 0x74, 0x18, 0x16],
+    [0x01, 0x02, 0xD3, 0x05, 0xC9, 0x95, 0x03, 0x02],
+    [0x61, 0x63, 0x0C, 0x00, 0x94, 0xC0, 0x33, 0xF6],
+    [0x21, 0x72, 0x0D, 0x00, 0xC1, 0xD5, 0x56, 0x06],
+];
+
+// frequency multipliers, doubled to keep the 1/2 step integral
+const MULTIPLIERS: [u32; 16] = [1, 2, 4, 6, 8, 10, 12, 14, 16, 18, 20, 20, 24, 24, 30, 30];
+const KEY_SCALE_LEVELS: [u16; 16] = [
+    0, 32, 40, 45, 48, 51, 53, 55, 56, 58, 59, 60, 61, 62, 63, 64,
+];
+const ENVELOPE_STEPS: [[u16; 8]; 4] = [
+    [0, 1, 0, 1, 0, 1, 0, 1],
+    [0, 1, 0, 1, 1, 1, 0, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1],
+    [0, 1, 1, 1, 1, 1, 1, 1],
+];
+const VIBRATO_STEPS: [i32; 8] = [0, 1, 2, 1, 0, -1, -2, -1];
+
+// 4.875 dB tremolo depth in envelope units
+const TREMOLO_DEPTH: u32 = 26;
+
+// quarter sine and exponent tables of the chip ROM, in 1/256 steps of a power of two
+const LOG_SIN: [u16; 256] = [
+    2137, 1731, 1543, 1419, 1326, 1252, 1190, 1137, 1091, 1050, 1013, 979, 949, 920, 894, 869, 846,
+    825, 804, 785, 767, 749, 732, 717, 701, 687, 672, 659, 646, 633, 621, 609, 598, 587, 576, 566,
+    556, 546, 536, 527, 518, 509, 501, 492, 484, 476, 468, 461, 453, 446, 439, 432, 425, 418, 411,
+    405, 399, 392, 386, 380, 375, 369, 363, 358, 352, 347, 341, 336, 331, 326, 321, 316, 311, 307,
+    302, 297, 293, 289, 284, 280, 276, 271, 267, 263, 259, 255, 251, 248, 244, 240, 236, 233, 229,
+    226, 222, 219, 215, 212, 209, 205, 202, 199, 196, 193, 190, 187, 184, 181, 178, 175, 172, 169,
+    167, 164, 161, 159, 156, 153, 151, 148, 146, 143, 141, 138, 136, 134, 131, 129, 127, 125, 122,
+    120, 118, 116, 114, 112, 110, 108, 106, 104, 102, 100, 98, 96, 94, 92, 91, 89, 87, 85, 83, 82,
+    80, 78, 77, 75, 74, 72, 70, 69, 67, 66, 64, 63, 62, 60, 59, 57, 56, 55, 53, 52, 51, 49, 48, 47,
+    46, 45, 43, 42, 41, 40, 39, 38, 37, 36, 35, 34, 33, 32, 31, 30, 29, 28, 27, 26, 25, 24, 23, 23,
+    22, 21, 20, 20, 19, 18, 17, 17, 16, 15, 15, 14, 13, 13, 12, 12, 11, 10, 10, 9, 9, 8, 8, 7, 7,
+    7, 6, 6, 5, 5, 5, 4, 4, 4, 3, 3, 3, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+const EXP: [u16; 256] = [
+    2042, 2037, 2031, 2026, 2020, 2015, 2010, 2004, 1999, 1993, 1988, 1983, 1977, 1972, 1966, 1961,
+    1956, 1951, 1945, 1940, 1935, 1930, 1924, 1919, 1914, 1909, 1904, 1898, 1893, 1888, 1883, 1878,
+    1873, 1868, 1863, 1858, 1853, 1848, 1843, 1838, 1833, 1828, 1823, 1818, 1813, 1808, 1803, 1798,
+    1794, 1789, 1784, 1779, 1774, 1769, 1765, 1760, 1755, 1750, 1746, 1741, 1736, 1732, 1727, 1722,
+    1717, 1713, 1708, 1704, 1699, 1694, 1690, 1685, 1681, 1676, 1672, 1667, 1663, 1658, 1654, 1649,
+    1645, 1640, 1636, 1631, 1627, 1623, 1618, 1614, 1609, 1605, 1601, 1596, 1592, 1588, 1584, 1579,
+    1575, 1571, 1566, 1562, 1558, 1554, 1550, 1545, 1541, 1537, 1533, 1529, 1525, 1520, 1516, 1512,
+    1508, 1504, 1500, 1496, 1492, 1488, 1484, 1480, 1476, 1472, 1468, 1464, 1460, 1456, 1452, 1448,
+    1444, 1440, 1436, 1433, 1429, 1425, 1421, 1417, 1413, 1409, 1406, 1402, 1398, 1394, 1391, 1387,
+    1383, 1379, 1376, 1372, 1368, 1364, 1361, 1357, 1353, 1350, 1346, 1342, 1339, 1335, 1332, 1328,
+    1324, 1321, 1317, 1314, 1310, 1307, 1303, 1300, 1296, 1292, 1289, 1286, 1282, 1279, 1275, 1272,
+    1268, 1265, 1261, 1258, 1255, 1251, 1248, 1244, 1241, 1238, 1234, 1231, 1228, 1224, 1221, 1218,
+    1214, 1211, 1208, 1205, 1201, 1198, 1195, 1192, 1188, 1185, 1182, 1179, 1176, 1172, 1169, 1166,
+    1163, 1160, 1157, 1154, 1150, 1147, 1144, 1141, 1138, 1135, 1132, 1129, 1126, 1123, 1120, 1117,
+    1114, 1111, 1108, 1105, 1102, 1099, 1096, 1093, 1090, 1087, 1084, 1081, 1078, 1075, 1072, 1069,
+    1066, 1064, 1061, 1058, 1055, 1052, 1049, 1046, 1044, 1041, 1038, 1035, 1032, 1030, 1027, 1024,
+];
+
+fn envelope_increment(rate: u8, counter: u32) -> u16 {
+    let (high, low) = (rate >> 2, rate as usize & 0b11);
+
+    match high {
+        0 => 0,
+        1..=12 => {
+            let shift = 13 - high as u32;
+
+            match counter & ((1 << shift) - 1) {
+                0 => ENVELOPE_STEPS[low][(counter >> shift) as usize & 7],
+                _ => 0,
+            }
+        }
+        _ => ENVELOPE_STEPS[low][counter as usize & 7] << (high - 12),
+    }
+}
+
+/// Signed output of the sine wave at a 10 bit phase, attenuated in 0.1875 dB steps.
+fn wave_output(phase: u32, attenuation: u32, rectified: bool) -> i32 {
+    let negative = phase.contains(9);
+
+    if negative && rectified {
+        return 0;
+    }
+
+    let index = match phase.contains(8) {
+        true => !phase & 0xFF,
+        false => phase & 0xFF,
+    };
+    let level = LOG_SIN[index as usize] as u32 + (attenuation << 3);
+    let value = ((EXP[level as usize & 0xFF] as u32) << 1) >> (level >> 8).min(31);
+
+    match negative {
+        true => -(value as i32),
+        false => value as i32,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EnvelopeState {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+#[derive(Debug)]
+struct Operator {
+    phase: u32,
+    envelope: u16,
+    state: EnvelopeState,
+    output: i16,
+    previous: i16,
+}
+
+impl Default for Operator {
+    fn default() -> Self {
+        Self {
+            phase: 0,
+            envelope: MAX_ATTENUATION,
+            state: EnvelopeState::Release,
+            output: 0,
+            previous: 0,
+        }
+    }
+}
+
+impl Operator {
+    fn key_on(&mut self) {
+        self.phase = 0;
+        self.state = EnvelopeState::Attack;
+    }
+
+    fn key_off(&mut self) {
+        self.state = EnvelopeState::Release;
+    }
+
+    fn clock_envelope(&mut self, rate: u8, sustain_level: u16, counter: u32) {
+        let increment = envelope_increment(rate, counter);
+
+        match self.state {
+            EnvelopeState::Attack if rate >= 60 => self.envelope = 0,
+            EnvelopeState::Attack if increment > 0 => {
+                let step = (self.envelope as u32 * increment as u32 + 7) >> 3;
+                self.envelope = self.envelope.saturating_sub(step as u16);
+            }
+            EnvelopeState::Attack => {}
+            EnvelopeState::Decay | EnvelopeState::Sustain | EnvelopeState::Release => {
+                self.envelope = (self.envelope + increment).min(MAX_ATTENUATION);
+            }
+        }
+
+        match self.state {
+            EnvelopeState::Attack if self.envelope == 0 => self.state = EnvelopeState::Decay,
+            EnvelopeState::Decay if self.envelope >= sustain_level => {
+                self.state = EnvelopeState::Sustain
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Snapshot for Operator {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u32(self.phase);
+        state.write_u16(self.envelope);
+        state.write_u8(self.state as u8);
+        state.write_u16(self.output as u16);
+        state.write_u16(self.previous as u16);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.phase = state.read_u32()? & 0x7FFFF;
+        self.envelope = state.read_u16()?.min(MAX_ATTENUATION);
+        self.state = match state.read_u8()? {
+            0 => EnvelopeState::Attack,
+            1 => EnvelopeState::Decay,
+            2 => EnvelopeState::Sustain,
+            3 => EnvelopeState::Release,
+            _ => return Err(Error::InvalidSaveState),
+        };
+        self.output = state.read_u16()? as i16;
+        self.previous = state.read_u16()? as i16;
+        Ok(())
+    }
+}
+
+/// YM2413 derivative with six two-operator FM channels and 15 built-in instruments.
+#[derive(Debug)]
+pub struct Vrc7Audio {
+    registers: [u8; REGISTER_COUNT],
+    address: u8,
+    operators: [[Operator; 2]; CHANNEL_COUNT],
+    counter: u32,
+    divider: u8,
+    output: i32,
+    silenced: bool,
+}
+
+impl Default for Vrc7Audio {
+    fn default() -> Self {
+        Self {
+            registers: [0; REGISTER_COUNT],
+            address: 0,
+            operators: Default::default(),
+            counter: 0,
+            divider: 0,
+            output: 0,
+            silenced: false,
+        }
+    }
+}
+
+impl Vrc7Audio {
+    pub fn write_address(&mut self, value: u8) {
+        self.address = value;
+    }
+
+    pub fn write_data(&mut self, value: u8) {
+        let address = self.address as usize;
+        let channel = address & 0x0F;
+
+        match address {
+            0x00..=0x07 => {}
+            0x10..=0x35 if channel < CHANNEL_COUNT => {}
+            _ => return,
+        }
+
+        let key_was_on = self.registers[0x20 + channel].contains(4);
+
+        self.registers[address] = value;
+
+        if address & 0xF0 != 0x20 {
+            return;
+        }
+
+        match (key_was_on, value.contains(4)) {
+            (false, true) => self.operators[channel]
+                .iter_mut()
+                .for_each(Operator::key_on),
+            (true, false) => self.operators[channel]
+                .iter_mut()
+                .for_each(Operator::key_off),
+            _ => {}
+        }
+    }
+
+    /// $E000 bit 6 holds the chip in reset, which silences it and clears its registers.
+    pub fn set_silenced(&mut self, silenced: bool) {
+        if silenced {
+            *self = Self::default();
+        }
+
+        self.silenced = silenced;
+    }
+
+    /// Advances by one CPU cycle.
+    pub fn tick(&mut self) {
+        if self.silenced {
+            return;
+        }
+
+        self.divider += 1;
+
+        if self.divider < CLOCK_DIVIDER {
+            return;
+        }
+
+        self.divider = 0;
+        self.output = (0..CHANNEL_COUNT)
+            .map(|channel| self.clock_channel(channel))
+            .sum();
+        self.counter = self.counter.wrapping_add(1);
+    }
+
+    fn patch(&self, channel: usize) -> [u8; 8] {
+        match self.registers[0x30 + channel] >> 4 {
+            0 => self.registers[0..8].try_into().unwrap(),
+            instrument => PATCHES[instrument as usize - 1],
+        }
+    }
+
+    fn clock_channel(&mut self, channel: usize) -> i32 {
+        let patch = self.patch(channel);
+        let control = self.registers[0x20 + channel];
+        let fnum = self.registers[0x10 + channel] as u32 | (control as u32 & 1) << 8;
+        let block = control.get_range(1..4) as u32;
+        let sustain = control.contains(5);
+        let volume = self.registers[0x30 + channel] as u32 & 0x0F;
+        let feedback = patch[3] & 0b111;
+        let counter = self.counter;
+
+        // 3.7 Hz triangle tremolo and 6.1 Hz vibrato
+        let tremolo_step = (counter >> 8) % (TREMOLO_DEPTH * 2);
+        let tremolo = match tremolo_step < TREMOLO_DEPTH {
+            true => tremolo_step,
+            false => TREMOLO_DEPTH * 2 - 1 - tremolo_step,
+        };
+        let vibrato = VIBRATO_STEPS[(counter >> 10) as usize & 7];
+
+        let mut modulation = 0;
+
+        for (index, operator) in self.operators[channel].iter_mut().enumerate() {
+            let flags = patch[index];
+            let multiplier = MULTIPLIERS[flags as usize & 0x0F];
+            let key_scale =
+                ((block << 1) | fnum >> 8) as u8 >> if flags.contains(4) { 0 } else { 2 };
+            let envelope_rates = patch[4 + index];
+            let release_rates = patch[6 + index];
+            let percussive = !flags.contains(5);
+
+            let base_rate = match operator.state {
+                EnvelopeState::Attack => envelope_rates >> 4,
+                EnvelopeState::Decay => envelope_rates & 0x0F,
+                EnvelopeState::Sustain if percussive => release_rates & 0x0F,
+                EnvelopeState::Sustain => 0,
+                EnvelopeState::Release if sustain => 5,
+                EnvelopeState::Release if percussive => 7,
+                EnvelopeState::Release => release_rates & 0x0F,
+            };
+            let rate = match base_rate {
+                0 => 0,
+                rate => (rate * 4 + key_scale).min(63),
+            };
+            let sustain_level = ((release_rates >> 4) as u16) << 4;
+
+            operator.clock_envelope(rate, sustain_level, counter);
+
+            let phase_fnum = match flags.contains(6) {
+                true => (fnum as i32 + (((fnum >> 6) as i32 * vibrato) >> 1)) as u32,
+                false => fnum,
+            };
+
+            operator.phase = (operator.phase + ((phase_fnum * multiplier) << block >> 1)) & 0x7FFFF;
+
+            let key_scale_level = match patch[2 + index] >> 6 {
+                0 => 0,
+                shift => {
+                    let level = ((KEY_SCALE_LEVELS[fnum as usize >> 5] as i32) << 2)
+                        - ((8 - block as i32) << 5);
+                    (level.max(0) as u32) >> (3 - shift)
+                }
+            };
+            let level = match index {
+                0 => (patch[2] as u32 & 0x3F) << 2,
+                _ => volume << 4,
+            };
+            let attenuation = operator.envelope as u32
+                + level
+                + key_scale_level
+                + if flags.contains(7) { tremolo } else { 0 };
+
+            let phase = match index {
+                0 if feedback > 0 => {
+                    let feedback_input = operator.previous as i32 + operator.output as i32;
+                    (operator.phase >> 9) as i32 + (feedback_input >> (9 - feedback))
+                }
+                0 => (operator.phase >> 9) as i32,
+                _ => (operator.phase >> 9) as i32 + (modulation >> 1),
+            };
+            let rectified = patch[3].contains(3 + index as u8);
+            let output = wave_output(
+                phase as u32 & 0x3FF,
+                attenuation.min(MAX_ATTENUATION as u32),
+                rectified,
+            );
+
+            operator.previous = operator.output;
+            operator.output = output as i16;
+            modulation = output;
+        }
+
+        modulation
+    }
+}
+
+impl ExpansionAudio for Vrc7Audio {
+    fn chip(&self) -> AudioChip {
+        AudioChip::Vrc7
+    }
+
+    fn get_sample(&self) -> f32 {
+        // the FM output is signed, it is centered around half scale
+        (self.output as f32 / MAX_OUTPUT + 1.0) / 2.0
+    }
+}
+
+impl Snapshot for Vrc7Audio {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_bytes(&self.registers);
+        state.write_u8(self.address);
+
+        for operator in self.operators.iter().flatten() {
+            operator.save(state);
+        }
+
+        state.write_u32(self.counter);
+        state.write_u8(self.divider);
+        state.write_u32(self.output as u32);
+        state.write_bool(self.silenced);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        state.read_bytes(&mut self.registers)?;
+        self.address = state.read_u8()?;
+
+        for operator in self.operators.iter_mut().flatten() {
+            operator.load(state)?;
+        }
+
+        self.counter = state.read_u32()?;
+        self.divider = state.read_u8()? % CLOCK_DIVIDER;
+        self.output = state.read_u32()? as i32;
+        self.silenced = state.read_bool()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Vrc7Audio, CLOCK_DIVIDER};
+    use crate::apu::ExpansionAudio;
+
+    fn write(audio: &mut Vrc7Audio, address: u8, value: u8) {
+        audio.write_address(address);
+        audio.write_data(value);
+    }
+
+    fn render(audio: &mut Vrc7Audio, samples: usize) -> Vec<f32> {
+        (0..samples)
+            .map(|_| {
+                for _ in 0..CLOCK_DIVIDER {
+                    audio.tick();
+                }
+
+                audio.get_sample()
+            })
+            .collect()
+    }
+
+    fn play_note(audio: &mut Vrc7Audio, instrument: u8) {
+        write(audio, 0x30, instrument << 4);
+        write(audio, 0x10, 0xAC);
+        write(audio, 0x20, 0x19); // key on, octave 4
+    }
+
+    #[test]
+    fn test_note_envelope() {
+        let mut audio = Vrc7Audio::default();
+
+        assert!(render(&mut audio, 100).iter().all(|&s| s == 0.5));
+
+        play_note(&mut audio, 3);
+
+        let attack = render(&mut audio, 2000);
+
+        assert!(attack.iter().any(|&s| (s - 0.5).abs() > 0.01));
+
+        write(&mut audio, 0x20, 0x08); // key off
+
+        let release = render(&mut audio, 50_000);
+
+        assert!(release[49_000..].iter().all(|&s| s == 0.5));
+    }
+
+    #[test]
+    fn test_custom_instrument() {
+        let mut builtin = Vrc7Audio::default();
+        let mut custom = Vrc7Audio::default();
+
+        for (address, value) in [0x03, 0x21, 0x05, 0x06, 0xE8, 0x81, 0x42, 0x27]
+            .into_iter()
+            .enumerate()
+        {
+            write(&mut custom, address as u8, value);
+        }
+
+        play_note(&mut builtin, 1);
+        play_note(&mut custom, 0);
+
+        assert_eq!(render(&mut builtin, 4000), render(&mut custom, 4000));
+    }
+
+    #[test]
+    fn test_key_scale_with_vibrato() {
+        let mut audio = Vrc7Audio::default();
+
+        write(&mut audio, 0x00, 0x40); // modulator vibrato
+        write(&mut audio, 0x02, 0xC0); // modulator key scale level 3
+        write(&mut audio, 0x04, 0xF0);
+        write(&mut audio, 0x05, 0xF0);
+        write(&mut audio, 0x10, 0xFF);
+        write(&mut audio, 0x20, 0x11); // key on, F-number $1FF
+
+        // the vibrato pushes the F-number past $1FF within a few thousand samples
+        assert!(render(&mut audio, 4000)
+            .iter()
+            .any(|&s| (s - 0.5).abs() > 0.01));
+    }
+
+    #[test]
+    fn test_silence_reset() {
+        let mut audio = Vrc7Audio::default();
+
+        play_note(&mut audio, 5);
+        render(&mut audio, 1000);
+        audio.set_silenced(true);
+
+        assert_eq!(audio.get_sample(), 0.5);
+
+        audio.set_silenced(false);
+
+        assert!(render(&mut audio, 1000).iter().all(|&s| s == 0.5));
+    }
+}
@@ -0,0 +1,169 @@
+// https://www.nesdev.org/wiki/VRC_IRQ
+
+use crate::{
+    error::Error,
+    utils::{BitFlag, Clock, Snapshot, SnapshotReader, SnapshotWriter},
+};
+
+const PRESCALER_PERIOD: i16 = 341;
+
+/// IRQ counter shared by the Konami VRC4, VRC6 and VRC7.
+#[derive(Debug)]
+pub struct VrcIrq {
+    latch: u8,
+    counter: u8,
+    prescaler: i16,
+    enabled: bool,
+    enable_after_ack: bool,
+    cycle_mode: bool,
+    interrupt: bool,
+}
+
+impl Default for VrcIrq {
+    fn default() -> Self {
+        Self {
+            latch: 0,
+            counter: 0,
+            prescaler: PRESCALER_PERIOD,
+            enabled: false,
+            enable_after_ack: false,
+            cycle_mode: false,
+            interrupt: false,
+        }
+    }
+}
+
+impl VrcIrq {
+    pub fn write_latch(&mut self, value: u8) {
+        self.latch = value;
+    }
+
+    pub fn write_latch_low(&mut self, value: u8) {
+        self.latch = (self.latch & 0xF0) | (value & 0x0F);
+    }
+
+    pub fn write_latch_high(&mut self, value: u8) {
+        self.latch = (self.latch & 0x0F) | (value << 4);
+    }
+
+    pub fn write_control(&mut self, value: u8) {
+        self.enable_after_ack = value.contains(0);
+        self.enabled = value.contains(1);
+        self.cycle_mode = value.contains(2);
+        self.interrupt = false;
+
+        if self.enabled {
+            self.counter = self.latch;
+            self.prescaler = PRESCALER_PERIOD;
+        }
+    }
+
+    pub fn acknowledge(&mut self) {
+        self.interrupt = false;
+        self.enabled = self.enable_after_ack;
+    }
+
+    pub fn irq(&self) -> bool {
+        self.interrupt
+    }
+
+    fn clock_counter(&mut self) {
+        if self.counter == 0xFF {
+            self.counter = self.latch;
+            self.interrupt = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
+
+impl Clock for VrcIrq {
+    fn tick(&mut self) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.cycle_mode {
+            self.clock_counter();
+            return;
+        }
+
+        // scanline mode divides the CPU clock by 113.667
+        self.prescaler -= 3;
+
+        if self.prescaler <= 0 {
+            self.prescaler += PRESCALER_PERIOD;
+            self.clock_counter();
+        }
+    }
+}
+
+impl Snapshot for VrcIrq {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.latch);
+        state.write_u8(self.counter);
+        state.write_u16(self.prescaler as u16);
+        state.write_bool(self.enabled);
+        state.write_bool(self.enable_after_ack);
+        state.write_bool(self.cycle_mode);
+        state.write_bool(self.interrupt);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.latch = state.read_u8()?;
+        self.counter = state.read_u8()?;
+        self.prescaler = state.read_u16()? as i16;
+        self.enabled = state.read_bool()?;
+        self.enable_after_ack = state.read_bool()?;
+        self.cycle_mode = state.read_bool()?;
+        self.interrupt = state.read_bool()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VrcIrq;
+    use crate::utils::Clock;
+
+    #[test]
+    fn test_vrc_irq_cycle_mode() {
+        let mut irq = VrcIrq::default();
+
+        irq.write_latch_low(0x0D);
+        irq.write_latch_high(0x0F);
+        irq.write_control(0b111);
+
+        irq.tick();
+        irq.tick();
+
+        assert!(!irq.irq());
+
+        irq.tick();
+
+        assert!(irq.irq());
+
+        irq.acknowledge();
+
+        assert!(!irq.irq());
+    }
+
+    #[test]
+    fn test_vrc_irq_scanline_mode() {
+        let mut irq = VrcIrq::default();
+
+        irq.write_latch_low(0x0F);
+        irq.write_latch_high(0x0F);
+        irq.write_control(0b010);
+
+        for _ in 0..113 {
+            irq.tick();
+        }
+
+        assert!(!irq.irq());
+
+        irq.tick();
+
+        assert!(irq.irq());
+    }
+}
@@ -1,4 +1,7 @@
-use crate::utils::BitPlane;
+use crate::{
+    error::Error,
+    utils::{BitPlane, Snapshot, SnapshotReader, SnapshotWriter},
+};
 
 #[derive(Debug, Default)]
 pub struct BackgroundData {
@@ -25,3 +28,23 @@ impl BackgroundData {
         self.palette_shift.high <<= 1;
     }
 }
+
+impl Snapshot for BackgroundData {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u16(self.address);
+        state.write_u8(self.pattern_id);
+        state.write_u8(self.palette_id);
+        self.pattern.save(state);
+        self.pattern_shift.save(state);
+        self.palette_shift.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.address = state.read_u16()?;
+        self.pattern_id = state.read_u8()?;
+        self.palette_id = state.read_u8()?;
+        self.pattern.load(state)?;
+        self.pattern_shift.load(state)?;
+        self.palette_shift.load(state)
+    }
+}
@@ -1,3 +1,8 @@
+use crate::{
+    error::Error,
+    utils::{Snapshot, SnapshotReader, SnapshotWriter},
+};
+
 const PRIMARY_OAM_SIZE: usize = 256;
 const SECONDARY_OAM_SIZE: usize = 32;
 
@@ -25,3 +30,30 @@ impl Default for OamData {
         }
     }
 }
+
+impl Snapshot for OamData {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.address);
+        state.write_u8(self.buffer);
+        state.write_bytes(&self.primary);
+        state.write_bytes(&self.secondary);
+        state.write_u8(self.primary_index);
+        state.write_u8(self.secondary_index);
+        state.write_bool(self.index_overflow);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.address = state.read_u8()?;
+        self.buffer = state.read_u8()?;
+        state.read_bytes(&mut self.primary)?;
+        state.read_bytes(&mut self.secondary)?;
+        self.primary_index = state.read_u8()?;
+        self.secondary_index = state.read_u8()?;
+        self.index_overflow = state.read_bool()?;
+
+        match self.secondary_index as usize <= SECONDARY_OAM_SIZE {
+            true => Ok(()),
+            false => Err(Error::InvalidSaveState),
+        }
+    }
+}
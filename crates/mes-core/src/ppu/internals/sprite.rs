@@ -1,4 +1,7 @@
-use crate::utils::BitPlane;
+use crate::{
+    error::Error,
+    utils::{BitPlane, Snapshot, SnapshotReader, SnapshotWriter},
+};
 
 #[derive(Debug, Default)]
 pub struct SpriteData {
@@ -28,3 +31,32 @@ impl SpriteData {
         self.pattern_shift[index].high = self.pattern_shift[index].high.reverse_bits();
     }
 }
+
+impl Snapshot for SpriteData {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_bytes(&self.buffer);
+        state.write_u16(self.address);
+        self.pattern_shift
+            .iter()
+            .for_each(|plane| plane.save(state));
+        state.write_bytes(&self.attribute_shift);
+        state.write_bytes(&self.offset_shift);
+        state.write_bool(self.zero_eval);
+        state.write_bool(self.zero_pixel);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        state.read_bytes(&mut self.buffer)?;
+        self.address = state.read_u16()?;
+
+        for plane in self.pattern_shift.iter_mut() {
+            plane.load(state)?;
+        }
+
+        state.read_bytes(&mut self.attribute_shift)?;
+        state.read_bytes(&mut self.offset_shift)?;
+        self.zero_eval = state.read_bool()?;
+        self.zero_pixel = state.read_bool()?;
+        Ok(())
+    }
+}
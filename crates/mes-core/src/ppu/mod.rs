@@ -5,9 +5,10 @@ mod registers;
 
 use crate::{
     bus::{Bus, PpuBus},
+    error::Error,
     mappers::MapperChip,
     ppu::{internals::*, registers::*},
-    utils::{BitFlag, Clock, Reset},
+    utils::{BitFlag, Clock, Reset, Snapshot, SnapshotReader, SnapshotWriter},
 };
 
 pub const SCREEN_WIDTH: usize = 256;
@@ -70,9 +71,26 @@ impl Ppu {
         self.vram_buffer
     }
 
-    pub fn read_status(&mut self) -> u8 {
+    /// Returns the value a read of `address` would return, without side effects.
+    pub fn peek_register(&self, address: u16) -> u8 {
+        match address & 0x2007 {
+            0x2002 => self.peek_status(),
+            0x2004 => self.read_oam_data(),
+            0x2007 => match self.v_addr.get() & 0x3FFF {
+                address @ 0x3F00..=0x3FFF => self.bus.peek_u8(address),
+                _ => self.vram_buffer,
+            },
+            _ => self.read_buffer(),
+        }
+    }
+
+    fn peek_status(&self) -> u8 {
         let mask = 0b1110_0000;
-        let status = (self.status.read() & mask) | (self.vram_buffer & !mask);
+        (self.status.read() & mask) | (self.vram_buffer & !mask)
+    }
+
+    pub fn read_status(&mut self) -> u8 {
+        let status = self.peek_status();
         self.latch = false;
         self.status.clear_vblank();
         status
@@ -160,6 +178,14 @@ impl Ppu {
         self.status.is_vblank()
     }
 
+    pub fn get_scanline(&self) -> u16 {
+        self.scanline
+    }
+
+    pub fn get_dot(&self) -> u16 {
+        self.dot
+    }
+
     pub fn get_frame_buffer(&self) -> &[u8] {
         self.frame_buffer.as_slice()
     }
@@ -314,10 +340,11 @@ impl Ppu {
         match cycle {
             0..=3 => self.sprite.buffer[cycle as usize] = oam_value,
             4 if y != 0xFF => self.sprite.address = self.get_sprite_pattern_address(),
+            4 => self.sprite.address = self.get_empty_sprite_pattern_address(),
             5 if y != 0xFF => {
                 self.sprite.pattern_shift[index].low = self.bus.read_u8(self.sprite.address)
             }
-            6 if y != 0xFF => self.sprite.address += 8,
+            6 => self.sprite.address += 8,
             7 if y != 0xFF => {
                 let attribute = self.sprite.buffer[2];
                 let sprite_x = self.sprite.buffer[3];
@@ -329,6 +356,9 @@ impl Ppu {
                     self.sprite.horizontal_reverse(index);
                 }
             }
+            5 | 7 => {
+                self.bus.read_u8(self.sprite.address); // dummy fetch, still visible to the mapper
+            }
             _ => {}
         }
 
@@ -358,6 +388,14 @@ impl Ppu {
         }
     }
 
+    // Unused sprite slots fetch tile $FF
+    fn get_empty_sprite_pattern_address(&self) -> u16 {
+        match self.ctrl.get_sprite_height() {
+            8 => self.ctrl.get_sprite_pattern_table_address() + 16 * 0xFF,
+            _ => 0x1000 + 16 * 0xFE,
+        }
+    }
+
     fn render_pixel(&mut self) {
         let x = self.dot.saturating_sub(1) as usize; // one dot offset
         let y = self.scanline as usize;
@@ -460,8 +498,10 @@ impl Clock for Ppu {
                     self.status.clear();
                 }
 
-                self.tick_sprite();
-                self.tick_background();
+                if self.mask.is_rendering() {
+                    self.tick_sprite();
+                    self.tick_background();
+                }
             }
             241 if self.dot == 1 => {
                 self.status.set_vblank();
@@ -514,10 +554,131 @@ impl Reset for Ppu {
     }
 }
 
+impl Snapshot for Ppu {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.vram_buffer);
+        self.ctrl.save(state);
+        self.mask.save(state);
+        self.status.save(state);
+        self.t_addr.save(state);
+        self.v_addr.save(state);
+        state.write_u8(self.fine_x);
+        state.write_bool(self.latch);
+        state.write_u64(self.cycle);
+        state.write_u16(self.dot);
+        state.write_u16(self.scanline);
+        state.write_bool(self.odd_frame);
+        state.write_bool(self.nmi.is_some());
+        self.oam.save(state);
+        self.bg.save(state);
+        self.sprite.save(state);
+        state.write_bytes(&self.frame_buffer);
+        self.bus.save(state);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.vram_buffer = state.read_u8()?;
+        self.ctrl.load(state)?;
+        self.mask.load(state)?;
+        self.status.load(state)?;
+        self.t_addr.load(state)?;
+        self.v_addr.load(state)?;
+        self.fine_x = state.read_u8()?;
+        self.latch = state.read_bool()?;
+        self.cycle = state.read_u64()?;
+        self.dot = state.read_u16()?;
+        self.scanline = state.read_u16()?;
+
+        if self.dot > 340 || self.scanline > 261 {
+            return Err(Error::InvalidSaveState);
+        }
+
+        self.odd_frame = state.read_bool()?;
+        self.nmi = state.read_bool()?.then_some(true);
+        self.oam.load(state)?;
+        self.bg.load(state)?;
+        self.sprite.load(state)?;
+        state.read_bytes(&mut self.frame_buffer)?;
+        self.bus.load(state)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Ppu;
-    use crate::mappers::MapperChip;
+    use crate::{
+        cartridge::{Cartridge, Mirroring},
+        error::Error,
+        mappers::{Mapper, MapperChip},
+        utils::{Clock, Snapshot, SnapshotReader, SnapshotWriter},
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    #[derive(Debug, Default)]
+    struct AddressLog {
+        cartridge: Cartridge,
+        addresses: Rc<RefCell<Vec<u16>>>,
+    }
+
+    impl Mapper for AddressLog {
+        fn read(&self, _address: u16) -> Option<u8> {
+            None
+        }
+
+        fn write(&mut self, _address: u16, _value: u8) {}
+
+        fn get_mirroring(&self) -> Mirroring {
+            self.cartridge.header.mirroring
+        }
+
+        fn get_cartridge(&self) -> &Cartridge {
+            &self.cartridge
+        }
+
+        fn get_cartridge_mut(&mut self) -> &mut Cartridge {
+            &mut self.cartridge
+        }
+
+        fn on_ppu_address(&mut self, address: u16) {
+            self.addresses.borrow_mut().push(address);
+        }
+    }
+
+    impl Clock for AddressLog {}
+
+    impl Snapshot for AddressLog {
+        fn save(&self, _state: &mut SnapshotWriter) {}
+
+        fn load(&mut self, _state: &mut SnapshotReader) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    fn ppu_with_log() -> (Ppu, Rc<RefCell<Vec<u16>>>) {
+        let log = AddressLog::default();
+        let addresses = log.addresses.clone();
+        let mut ppu = Ppu::new(MapperChip::new(log));
+
+        for address in 0..=255 {
+            ppu.write_oam(address, 0xFF);
+        }
+
+        (ppu, addresses)
+    }
+
+    fn sprite_fetches(ppu: &mut Ppu, addresses: &RefCell<Vec<u16>>) -> Vec<u16> {
+        for _ in 0..341 {
+            ppu.tick();
+        }
+
+        let addresses = addresses.borrow();
+        let sprite_table = 0x1000..0x2000;
+        addresses
+            .iter()
+            .copied()
+            .filter(|address| sprite_table.contains(address))
+            .collect()
+    }
 
     #[test]
     fn test_ppu_oam_read_write() {
@@ -558,4 +719,65 @@ mod tests {
         assert_eq!(buffer, 0x00);
         assert_eq!(data, 0x45);
     }
+
+    #[test]
+    fn test_ppu_rendering_disabled() {
+        let (mut ppu, addresses) = ppu_with_log();
+
+        for _ in 0..341 * 262 {
+            ppu.tick();
+        }
+
+        assert!(addresses.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_ppu_sprite_fetch_addresses() {
+        let (mut ppu, addresses) = ppu_with_log();
+
+        ppu.write_ctrl(0x08);
+        ppu.write_mask(0x18);
+        ppu.write_oam(0, 0);
+        ppu.write_oam(1, 2);
+        ppu.write_oam(2, 0);
+
+        let fetches = sprite_fetches(&mut ppu, &addresses);
+
+        assert_eq!(fetches.len(), 16);
+        assert_eq!(fetches[..2], [0x1020, 0x1028]);
+        assert!(fetches[2..].chunks(2).all(|f| f == [0x1FF0, 0x1FF8]));
+    }
+
+    #[test]
+    fn test_ppu_empty_sprite_fetch_addresses_8x16() {
+        let (mut ppu, addresses) = ppu_with_log();
+
+        ppu.write_ctrl(0x20);
+        ppu.write_mask(0x18);
+
+        let fetches = sprite_fetches(&mut ppu, &addresses);
+
+        assert_eq!(fetches.len(), 16);
+        assert!(fetches.chunks(2).all(|f| f == [0x1FE0, 0x1FE8]));
+    }
+
+    #[test]
+    fn test_ppu_invalid_save_state() {
+        let (mut ppu, _) = ppu_with_log();
+
+        for (dot, scanline, secondary_index) in [(341, 0, 0), (0, 262, 0), (0, 0, 33)] {
+            ppu.dot = dot;
+            ppu.scanline = scanline;
+            ppu.oam.secondary_index = secondary_index;
+
+            let mut state = SnapshotWriter::default();
+            ppu.save(&mut state);
+            let bytes = state.into_bytes();
+
+            let (mut loaded, _) = ppu_with_log();
+            let result = loaded.load(&mut SnapshotReader::new(&bytes));
+
+            assert_eq!(result, Err(Error::InvalidSaveState));
+        }
+    }
 }
@@ -1,5 +1,10 @@
 // https://www.nesdev.org/wiki/PPU_scrolling
 
+use crate::{
+    error::Error,
+    utils::{Snapshot, SnapshotReader, SnapshotWriter},
+};
+
 /// yyy NN YYYYY XXXXX
 /// ||| || ||||| +++++-- coarse X scroll
 /// ||| || +++++-------- coarse Y scroll
@@ -102,6 +107,17 @@ impl AddressRegister {
     }
 }
 
+impl Snapshot for AddressRegister {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u16(self.0);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.0 = state.read_u16()?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::AddressRegister;
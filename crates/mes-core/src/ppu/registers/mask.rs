@@ -1,4 +1,7 @@
-use crate::utils::BitFlag;
+use crate::{
+    error::Error,
+    utils::{BitFlag, Snapshot, SnapshotReader, SnapshotWriter},
+};
 
 #[allow(unused)]
 #[rustfmt::skip]
@@ -51,3 +54,14 @@ impl MaskRegister {
         self.0.contains(mask_flag::S)
     }
 }
+
+impl Snapshot for MaskRegister {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.0);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.0 = state.read_u8()?;
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
-use crate::utils::BitFlag;
+use crate::{
+    error::Error,
+    utils::{BitFlag, Snapshot, SnapshotReader, SnapshotWriter},
+};
 
 mod status_flag {
     pub const O: u8 = 5;
@@ -55,3 +58,14 @@ impl StatusRegister {
         self.0 &= 0b0001_1111;
     }
 }
+
+impl Snapshot for StatusRegister {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.0);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.0 = state.read_u8()?;
+        Ok(())
+    }
+}
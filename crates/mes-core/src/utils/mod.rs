@@ -1,8 +1,14 @@
 use std::ops::{BitAnd, BitAndAssign, BitOrAssign, Not, Range, Shl, Shr, Sub};
 
+use crate::error::Error;
+
+mod snapshot;
+
 #[cfg(test)]
 pub mod test;
 
+pub use snapshot::{Snapshot, SnapshotReader, SnapshotWriter};
+
 pub trait BitFlag<T> {
     fn get(&self, flag: T) -> T;
     fn get_range(&self, range: Range<T>) -> T;
@@ -64,6 +70,32 @@ pub struct BitPlane<T> {
     pub high: T,
 }
 
+impl Snapshot for BitPlane<u8> {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u8(self.low);
+        state.write_u8(self.high);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.low = state.read_u8()?;
+        self.high = state.read_u8()?;
+        Ok(())
+    }
+}
+
+impl Snapshot for BitPlane<u16> {
+    fn save(&self, state: &mut SnapshotWriter) {
+        state.write_u16(self.low);
+        state.write_u16(self.high);
+    }
+
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error> {
+        self.low = state.read_u16()?;
+        self.high = state.read_u16()?;
+        Ok(())
+    }
+}
+
 pub trait Clock {
     fn tick(&mut self) {}
 }
@@ -0,0 +1,158 @@
+use crate::error::Error;
+
+/// Component state that can be written to and restored from a save state.
+pub trait Snapshot {
+    fn save(&self, state: &mut SnapshotWriter);
+    fn load(&mut self, state: &mut SnapshotReader) -> Result<(), Error>;
+}
+
+#[derive(Debug, Default)]
+pub struct SnapshotWriter(Vec<u8>);
+
+impl SnapshotWriter {
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_option_u8(&mut self, value: Option<u8>) {
+        self.write_bool(value.is_some());
+        self.write_u8(value.unwrap_or_default());
+    }
+
+    /// Writes a length-prefixed byte slice.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_u32(bytes.len() as u32);
+        self.0.extend_from_slice(bytes);
+    }
+}
+
+#[derive(Debug)]
+pub struct SnapshotReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> SnapshotReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + N)
+            .ok_or(Error::eof("save state data", N))?;
+
+        self.position += N;
+
+        Ok(bytes.try_into().unwrap())
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, Error> {
+        self.take::<1>().map(|[value]| value)
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, Error> {
+        self.read_u8().map(|value| value != 0)
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, Error> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, Error> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, Error> {
+        self.take().map(u64::from_le_bytes)
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32, Error> {
+        self.take().map(f32::from_le_bytes)
+    }
+
+    pub fn read_option_u8(&mut self) -> Result<Option<u8>, Error> {
+        let is_some = self.read_bool()?;
+        let value = self.read_u8()?;
+        Ok(is_some.then_some(value))
+    }
+
+    /// Reads a length-prefixed byte slice into `buffer`, the lengths must match.
+    pub fn read_bytes(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        let length = self.read_u32()? as usize;
+
+        if length != buffer.len() {
+            return Err(Error::InvalidSaveState);
+        }
+
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + length)
+            .ok_or(Error::eof("save state data", length))?;
+
+        buffer.copy_from_slice(bytes);
+        self.position += length;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SnapshotReader, SnapshotWriter};
+    use crate::error::Error;
+
+    #[test]
+    fn test_snapshot_read_write() {
+        let mut writer = SnapshotWriter::default();
+
+        writer.write_u8(0x12);
+        writer.write_u16(0x3456);
+        writer.write_option_u8(None);
+        writer.write_bytes(&[1, 2, 3]);
+
+        let bytes = writer.into_bytes();
+        let mut reader = SnapshotReader::new(&bytes);
+        let mut buffer = [0; 3];
+
+        assert_eq!(reader.read_u8(), Ok(0x12));
+        assert_eq!(reader.read_u16(), Ok(0x3456));
+        assert_eq!(reader.read_option_u8(), Ok(None));
+        assert_eq!(reader.read_bytes(&mut buffer), Ok(()));
+        assert_eq!(buffer, [1, 2, 3]);
+        assert!(reader.is_empty());
+        assert!(matches!(
+            reader.read_u8(),
+            Err(Error::UnexpectedEndOfInput { .. })
+        ));
+    }
+}
@@ -1,10 +1,120 @@
 use std::{error::Error, fmt};
 
 use super::BitFlag;
+use crate::{
+    cartridge::{Cartridge, Header},
+    Nes,
+};
 
 pub const NESTEST_ROM: &[u8] = include_bytes!("../../../../nes-test-roms/other/nestest.nes");
 pub const NESTEST_LOG: &str = include_str!("../../../../nes-test-roms/other/nestest.log");
 
+/// Loads a ROM from the nes-test-roms checkout at runtime
+pub fn test_rom(path: &str) -> Vec<u8> {
+    let root = concat!(env!("CARGO_MANIFEST_DIR"), "/../../nes-test-roms");
+    std::fs::read(format!("{root}/{path}"))
+        .unwrap_or_else(|err| panic!("{path}: {err}, is the nes-test-roms submodule checked out?"))
+}
+
+/// Runs a ROM reporting through blargg's protocol at $6000 and returns its status and text output
+pub fn run_blargg_rom(rom: &[u8]) -> (u8, String) {
+    let mut nes = Nes::new(rom).unwrap();
+
+    for _ in 0..1800 {
+        nes.step_frame();
+
+        let signature = [0x6001, 0x6002, 0x6003].map(|address| nes.peek_cpu(address));
+        let status = nes.peek_cpu(0x6000);
+
+        match status {
+            0x80 => {} // running
+            0x81 => {
+                nes.step_frame(); // reset requested, holds it for a few frames
+                nes.step_frame();
+                nes.reset();
+            }
+            _ if signature == [0xDE, 0xB0, 0x61] => {
+                let output = (0x6004..0x7FFF)
+                    .map(|address| nes.peek_cpu(address))
+                    .take_while(|&byte| byte != 0)
+                    .map(char::from)
+                    .collect();
+
+                return (status, output);
+            }
+            _ => {}
+        }
+    }
+
+    panic!("the ROM did not report a result");
+}
+
+/// Builds a 32 KB NROM image running `program` from 0x8000
+pub fn nrom(program: &[u8]) -> Vec<u8> {
+    let mut rom = vec![0; 16 + 0x8000 + 0x2000];
+    rom[..6].copy_from_slice(&[0x4E, 0x45, 0x53, 0x1A, 2, 1]);
+    rom[16..16 + program.len()].copy_from_slice(program);
+    rom[16 + 0x7FFC..16 + 0x7FFE].copy_from_slice(&[0x00, 0x80]); // reset vector
+    rom
+}
+
+/// Builds a cartridge with 8 KB of PRG RAM where every 8 KB PRG bank and 1 KB CHR bank
+/// is filled with its own index, boards without CHR ROM get 8 KB of CHR RAM
+pub fn banked_cartridge(mapper: u16, prg_rom_pages: u16, chr_rom_pages: u16) -> Cartridge {
+    let prg_banks = prg_rom_pages as usize * 2;
+    let chr_banks = chr_rom_pages as usize * 8;
+
+    Cartridge {
+        header: Header {
+            prg_rom_pages,
+            chr_rom_pages,
+            mapper,
+            ..Cartridge::default().header
+        },
+        prg_rom: (0..prg_banks)
+            .flat_map(|bank| vec![bank as u8; 0x2000])
+            .collect(),
+        chr_rom: (0..chr_banks)
+            .flat_map(|bank| vec![bank as u8; 0x400])
+            .collect(),
+        prg_ram: vec![0; 0x2000],
+        chr_ram: vec![0; (chr_rom_pages == 0) as usize * 0x2000],
+        ..Default::default()
+    }
+}
+
+/// Deterministic xorshift generator for fuzz-style tests
+pub struct XorShift(u64);
+
+impl XorShift {
+    pub fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        self.next_u64() as u8
+    }
+
+    pub fn next_u16(&mut self) -> u16 {
+        self.next_u64() as u16
+    }
+
+    pub fn below(&mut self, bound: usize) -> usize {
+        self.next_u64() as usize % bound
+    }
+
+    pub fn bytes(&mut self, length: usize) -> Vec<u8> {
+        (0..length).map(|_| self.next_u8()).collect()
+    }
+}
+
 /// Represents parsed lines from nestest.log
 pub struct LogLine {
     pub pc: u16,
@@ -159,6 +159,34 @@ pub extern "C" fn Java_dev_luckasranarison_mes_lib_Nes_setControllerState(
     nes.unwrap_mut().set_controller_state(id, state);
 }
 
+#[no_mangle]
+pub extern "C" fn Java_dev_luckasranarison_mes_lib_Nes_saveState<'local>(
+    env: JNIEnv<'static>,
+    _class: JClass,
+    nes: *const Nes,
+) -> JByteArray<'local> {
+    let state = nes.unwrap_ref().save_state();
+
+    env.byte_array_from_slice(&state)
+        .expect("Failed to create save state array")
+}
+
+#[no_mangle]
+pub extern "C" fn Java_dev_luckasranarison_mes_lib_Nes_loadState(
+    mut env: JNIEnv<'static>,
+    _class: JClass,
+    nes: *mut Nes,
+    state: JByteArray,
+) {
+    let buffer = env
+        .convert_byte_array(state)
+        .expect("Failed to load save state");
+
+    if let Err(err) = nes.unwrap_mut().load_state(&buffer) {
+        env.throw(err.to_string()).unwrap();
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn Java_dev_luckasranarison_mes_lib_Nes_fillFrameBuffer(
     mut env: JNIEnv<'static>,
@@ -62,6 +62,31 @@ impl Nes {
         self.engine.clear_audio_buffer();
     }
 
+    #[wasm_bindgen(js_name = "saveState")]
+    pub fn save_state(&self) -> Vec<u8> {
+        self.engine.save_state()
+    }
+
+    #[wasm_bindgen(js_name = "loadState")]
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), JsError> {
+        Ok(self.engine.load_state(state)?)
+    }
+
+    #[wasm_bindgen(js_name = "getBatteryRam")]
+    pub fn get_battery_ram(&self) -> Option<Vec<u8>> {
+        self.engine.battery_ram().map(|ram| ram.to_vec())
+    }
+
+    #[wasm_bindgen(js_name = "loadBatteryRam")]
+    pub fn load_battery_ram(&mut self, bytes: &[u8]) {
+        self.engine.load_battery_ram(bytes);
+    }
+
+    #[wasm_bindgen(js_name = "takeBatteryRamDirty")]
+    pub fn take_battery_ram_dirty(&mut self) -> bool {
+        self.engine.take_battery_ram_dirty()
+    }
+
     #[wasm_bindgen(js_name = "updateImageData")]
     pub fn update_image_data(&mut self) -> Result<ImageData, JsValue> {
         let frame = self.update_frame();